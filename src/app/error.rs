@@ -1,3 +1,5 @@
+use crate::report::ThresholdGroup;
+
 const EXIT_FAIL_UNDER: i32 = 1;
 const EXIT_USAGE: i32 = 2;
 
@@ -28,4 +30,63 @@ impl AppError {
             EXIT_FAIL_UNDER,
         )
     }
+
+    pub fn fail_under_branches(percent: f64, threshold: f64) -> Self {
+        Self::new(
+            format!(
+                "Branch coverage {:.2}% is below --fail-under-branches {:.2}%",
+                percent, threshold
+            ),
+            EXIT_FAIL_UNDER,
+        )
+    }
+
+    pub fn fail_under_total(percent: f64, threshold: f64) -> Self {
+        Self::new(
+            format!(
+                "Project coverage {:.2}% is below --fail-under-total {:.2}%",
+                percent, threshold
+            ),
+            EXIT_FAIL_UNDER,
+        )
+    }
+
+    pub fn thresholds(missed: &[&ThresholdGroup]) -> Self {
+        let groups: Vec<String> = missed
+            .iter()
+            .map(|group| {
+                format!(
+                    "{} {:.2}% < {:.2}%",
+                    group.pattern,
+                    group.coverage_percent(),
+                    group.threshold
+                )
+            })
+            .collect();
+        Self::new(
+            format!("Coverage is below --threshold for {}", groups.join(", ")),
+            EXIT_FAIL_UNDER,
+        )
+    }
+
+    pub fn regressions(count: usize) -> Self {
+        Self::new(
+            format!("{count} line(s) lost coverage since --base-coverage (--fail-on-regressions)"),
+            EXIT_FAIL_UNDER,
+        )
+    }
+
+    pub fn stale_coverage(count: usize) -> Self {
+        Self::new(
+            format!("coverage looks stale: {count} sign(s) it predates the sources (--stale-coverage fail)"),
+            EXIT_FAIL_UNDER,
+        )
+    }
+
+    pub fn uncalled_functions(count: usize) -> Self {
+        Self::new(
+            format!("{count} changed function(s) were never called (--fail-on-uncalled-functions)"),
+            EXIT_FAIL_UNDER,
+        )
+    }
 }
@@ -1,21 +1,212 @@
-use std::io::{Seek, SeekFrom};
+use std::io::{BufRead, Cursor, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
 
+use crate::cli::DiffFileArg;
 use crate::coverage::CoverageParser;
+use crate::util::path::{strip_path_prefix, PathMap};
+use crate::util::source::SourceTree;
 use crate::{coverage, diff};
 
-pub(crate) fn load_changed_files(path: &Path) -> Result<Vec<diff::types::ChangedFile>, String> {
+/// Where the changed lines come from: diff files, lines picked with `--lines`/`--lines-file`, two
+/// source trees compared with `--old-tree`/`--new-tree`, or a `--base`...`--head` range in git.
+pub(crate) struct ChangeSource {
+    pub(crate) diff_files: Vec<DiffFileArg>,
+    pub(crate) line_specs: Vec<String>,
+    pub(crate) line_files: Vec<PathBuf>,
+    pub(crate) trees: Option<(PathBuf, PathBuf)>,
+    pub(crate) revisions: Option<(String, String)>,
+}
+
+impl ChangeSource {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.diff_files.is_empty()
+            && self.line_specs.is_empty()
+            && self.line_files.is_empty()
+            && self.trees.is_none()
+            && self.revisions.is_none()
+    }
+
+    /// Where the new side of the changes can be read: the `--head` commit, the `--new-tree`
+    /// directory, or else the working tree.
+    pub(crate) fn source_tree(&self) -> Result<SourceTree, String> {
+        if let Some((_, head)) = &self.revisions {
+            return Ok(SourceTree::commit(crate::vcs::git::resolve_commit(head)?));
+        }
+        Ok(match &self.trees {
+            Some((_, new_tree)) if new_tree.is_dir() => SourceTree::Directory(new_tree.clone()),
+            _ => SourceTree::default(),
+        })
+    }
+}
+
+pub(crate) fn load_changed_files(
+    source: &ChangeSource,
+    strip_prefixes: &[String],
+) -> Result<Vec<diff::types::ChangedFile>, String> {
+    if !source.diff_files.is_empty() {
+        // Strip prefixes before each diff's own PREFIX= mapping is applied.
+        return load_diff_files(&source.diff_files, strip_prefixes);
+    }
+    let mut changed = if let Some((base, head)) = &source.revisions {
+        let base = crate::vcs::git::resolve_commit(base)?;
+        let head = crate::vcs::git::resolve_commit(head)?;
+        crate::vcs::git::diff(&[&format!("{base}...{head}")])?
+    } else if let Some((old_tree, new_tree)) = &source.trees {
+        diff::tree::diff_trees(old_tree, new_tree).map_err(|err| {
+            format!(
+                "Failed to compare {} with {}: {err}",
+                old_tree.display(),
+                new_tree.display()
+            )
+        })?
+    } else {
+        load_line_selection(&source.line_specs, &source.line_files)?
+    };
+    strip_prefixes_from(&mut changed, strip_prefixes);
+    Ok(changed)
+}
+
+/// Loads the lines changed between a previously reviewed head and the current one.
+pub(crate) fn load_changes_since(
+    reviewed_head: &str,
+    head: &str,
+    strip_prefixes: &[String],
+) -> Result<Vec<diff::types::ChangedFile>, String> {
+    let reviewed_head = crate::vcs::git::resolve_commit(reviewed_head)?;
+    let head = crate::vcs::git::resolve_commit(head)?;
+    let mut changed = crate::vcs::git::diff(&[&reviewed_head, &head])?;
+    strip_prefixes_from(&mut changed, strip_prefixes);
+    Ok(changed)
+}
+
+fn strip_prefixes_from(changed: &mut [diff::types::ChangedFile], strip_prefixes: &[String]) {
+    if strip_prefixes.is_empty() {
+        return;
+    }
+    for file in changed {
+        file.path = strip_path_prefix(&file.path, strip_prefixes).to_string();
+    }
+}
+
+fn load_line_selection(
+    specs: &[String],
+    files: &[PathBuf],
+) -> Result<Vec<diff::types::ChangedFile>, String> {
+    let mut selection = diff::selection::LineSelection::default();
+    for spec in specs {
+        selection.add_spec(spec)?;
+    }
+    for path in files {
+        let file = std::fs::File::open(path)
+            .map_err(|err| format!("Failed to read lines file {}: {err}", path.display()))?;
+        selection
+            .add_json(std::io::BufReader::new(file))
+            .map_err(|err| format!("Failed to parse lines file {}: {err}", path.display()))?;
+    }
+    Ok(selection.into_changed_files())
+}
+
+fn load_diff_files(
+    diff_files: &[DiffFileArg],
+    strip_prefixes: &[String],
+) -> Result<Vec<diff::types::ChangedFile>, String> {
+    let mut patches = Vec::new();
+    for diff_file in diff_files {
+        let path = &diff_file.path;
+        let file_patches = load_diff_file(path)
+            .map_err(|err| format!("Failed to parse diff file {}: {err}", path.display()))?;
+        for mut patch in file_patches {
+            strip_prefixes_from(&mut patch.files, strip_prefixes);
+            for path in &mut patch.deleted {
+                *path = strip_path_prefix(path, strip_prefixes).to_string();
+            }
+            if let Some(prefix) = &diff_file.prefix {
+                for file in &mut patch.files {
+                    file.path = format!("{prefix}/{}", file.path);
+                    if let Some(old_path) = &mut file.old_path {
+                        *old_path = format!("{prefix}/{old_path}");
+                    }
+                }
+                for path in &mut patch.deleted {
+                    *path = format!("{prefix}/{path}");
+                }
+            }
+            patches.push(patch);
+        }
+    }
+
+    Ok(if patches.len() == 1 {
+        patches.pop().map(|patch| patch.files).unwrap_or_default()
+    } else {
+        diff::series::combine_patches(patches)
+    })
+}
+
+/// Parses one diff file into its patches; an mbox yields one patch per message. Saved GitHub and
+/// GitLab API payloads are recognized by their leading JSON bracket.
+fn load_diff_file(path: &Path) -> Result<Vec<diff::types::Patch>, String> {
     let file = std::fs::File::open(path).map_err(|err| err.to_string())?;
-    let reader = std::io::BufReader::new(file);
-    diff::git::parse_unified_diff(reader).map_err(|err| err.to_string())
+    let mut reader = std::io::BufReader::new(file);
+    let sample = reader.fill_buf().map_err(|err| err.to_string())?;
+    if diff::api::can_parse(sample) {
+        let api_diff = diff::api::parse_api_diff(reader)?;
+        for truncated in &api_diff.truncated {
+            eprintln!(
+                "Warning: {} has no complete patch in {}; its changed lines may be missing.",
+                truncated,
+                path.display()
+            );
+        }
+        if api_diff.overflow {
+            eprintln!(
+                "Warning: {} lists only part of the merge request changes.",
+                path.display()
+            );
+        }
+        return Ok(vec![diff::types::Patch {
+            files: api_diff.files,
+            deleted: api_diff.deleted,
+        }]);
+    }
+    let is_context = diff::context::can_parse(sample).map_err(|err| err.to_string())?;
+    let is_mbox = String::from_utf8_lossy(sample)
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .is_some_and(diff::series::is_mbox);
+
+    if is_mbox {
+        let messages = diff::series::split_mbox(reader).map_err(|err| err.to_string())?;
+        return messages
+            .iter()
+            .map(|message| {
+                diff::git::parse_unified_patch(Cursor::new(message)).map_err(|err| err.to_string())
+            })
+            .collect();
+    }
+
+    let patch = if is_context {
+        diff::context::parse_context_patch(reader)
+    } else {
+        diff::git::parse_unified_patch(reader)
+    }
+    .map_err(|err| err.to_string())?;
+    Ok(vec![patch])
 }
 
 pub(crate) fn load_coverage_files(
     paths: &[PathBuf],
+    path_maps: &[PathMap],
+    source: &SourceTree,
 ) -> Result<coverage::store::CoverageStore, String> {
     let mut store = coverage::store::CoverageStore::default();
     for path in paths {
-        load_coverage_file(path, &mut store)?;
+        // Each file is loaded on its own so that hit counts add up across test runs.
+        let mut report = coverage::store::CoverageStore::default();
+        load_coverage_file(path, source, &mut report)?;
+        if !path_maps.is_empty() {
+            report.rename_files(|path| path_maps.iter().find_map(|map| map.apply(path)));
+        }
+        store.add_report(report);
     }
     store.prepare_lookup();
     Ok(store)
@@ -23,9 +214,10 @@ pub(crate) fn load_coverage_files(
 
 fn load_coverage_file(
     path: &Path,
+    source: &SourceTree,
     store: &mut coverage::store::CoverageStore,
 ) -> Result<(), String> {
-    let cobertura = coverage::cobertura::CoberturaParser;
+    let cobertura = coverage::cobertura::CoberturaParser::new(source);
     if try_parse_coverage(&cobertura, path, store)? {
         return Ok(());
     }
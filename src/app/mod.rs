@@ -4,44 +4,233 @@ mod output;
 mod reporting;
 mod validation;
 
-use crate::{cli, coverage};
+use crate::{cli, coverage, diff, report};
 use error::AppError;
-use files::{collect_coverage_files, load_changed_files, load_coverage_files};
+use files::{
+    collect_coverage_files, load_changed_files, load_changes_since, load_coverage_files,
+    ChangeSource,
+};
 use output::build_output_plan;
 use reporting::write_reports;
-use validation::{validate_fail_under, validate_output_requirements};
+use validation::{validate_fail_under, validate_gate, validate_head, validate_output_requirements};
 
 pub fn run(options: cli::CliOptions) -> Result<(), AppError> {
-    let diff_file = options.diff_file;
+    validate_head(options.base.as_deref(), options.head.as_deref())?;
+    let changes = ChangeSource {
+        diff_files: options.diff_files,
+        line_specs: options.lines,
+        line_files: options.lines_files,
+        trees: options.old_tree.zip(options.new_tree),
+        revisions: options.base.map(|base| {
+            let head = options.head.clone().unwrap_or_else(|| "HEAD".to_string());
+            (base, head)
+        }),
+    };
     let coverage_paths = options.coverage_paths;
     let fail_under = options.fail_under;
+    let fail_under_branches = options.fail_under_branches;
+    let fail_under_total = options.fail_under_total;
+    let total_scope = options.total_scope;
+    let fail_on_uncalled_functions = options.fail_on_uncalled_functions;
+    let thresholds = options.thresholds;
+    let base_coverage_paths = options.base_coverage;
+    let fail_on_regressions = options.fail_on_regressions;
     let output_targets = options.outputs;
     let missing_coverage = options.missing_coverage;
+    let ambiguous_paths = options.ambiguous_paths;
+    let stale_coverage = options.stale_coverage;
+    let min_hits = options.min_hits;
+    let ignore_whitespace = options.ignore_whitespace;
+    let attribute_continuation_lines = options.attribute_continuation_lines;
+    let moved_lines_mode = options.moved_lines;
+    let moved_min_lines = options.moved_min_lines;
+    let strip_prefixes = options.strip_prefixes;
+    let path_maps = options.path_maps;
+    let include_patterns = options.include;
+    let exclude_patterns = options.exclude;
+    let exclude_markers = options.exclude_markers;
+    let reviewed_head = options.reviewed_head;
+    let head = options.head.unwrap_or_else(|| "HEAD".to_string());
 
     let coverage_files = collect_coverage_files(coverage_paths).map_err(AppError::usage)?;
 
-    validate_fail_under(fail_under, diff_file.as_ref(), &coverage_files)?;
+    validate_fail_under("--fail-under", fail_under, &changes, &coverage_files)?;
+    validate_fail_under(
+        "--fail-under-branches",
+        fail_under_branches,
+        &changes,
+        &coverage_files,
+    )?;
+    validate_fail_under(
+        "--fail-under-total",
+        fail_under_total,
+        &changes,
+        &coverage_files,
+    )?;
+    validate_gate(
+        "--fail-on-uncalled-functions",
+        fail_on_uncalled_functions,
+        &changes,
+        &coverage_files,
+    )?;
+    let base_coverage_files = if base_coverage_paths.is_empty() {
+        Vec::new()
+    } else {
+        collect_coverage_files(base_coverage_paths).map_err(AppError::usage)?
+    };
+    validate_gate(
+        "--base-coverage",
+        !base_coverage_files.is_empty(),
+        &changes,
+        &coverage_files,
+    )?;
+    validate_gate(
+        "--threshold",
+        !thresholds.is_empty(),
+        &changes,
+        &coverage_files,
+    )?;
 
     let output_plan = build_output_plan(output_targets)?;
-    validate_output_requirements(&output_plan, diff_file.as_ref(), &coverage_files)?;
+    validate_output_requirements(&output_plan, &changes, &coverage_files)?;
 
-    match (diff_file, coverage_files.is_empty()) {
-        (Some(diff_file), false) => {
-            let changed = load_changed_files(&diff_file).map_err(|err| {
-                AppError::usage(format!(
-                    "Failed to parse diff file {}: {err}",
-                    diff_file.display()
-                ))
-            })?;
+    match (changes.is_empty(), coverage_files.is_empty()) {
+        (false, false) => {
+            let mut changed =
+                load_changed_files(&changes, &strip_prefixes).map_err(AppError::usage)?;
+            let source = changes.source_tree().map_err(AppError::usage)?;
+            // Regressions are mapped through the full diff, before any lines are filtered out.
+            let full_diff = (!base_coverage_files.is_empty()).then(|| changed.clone());
+            let mut skipped: Vec<(String, String)> =
+                diff::attributes::skip_generated_files(&mut changed, &source)
+                    .into_iter()
+                    .map(|(path, reason)| (path, reason.label().to_string()))
+                    .collect();
+            skipped.extend(diff::filter::filter_paths(
+                &mut changed,
+                &include_patterns,
+                &exclude_patterns,
+            ));
+            let excluded_whitespace_lines = if ignore_whitespace {
+                diff::whitespace::exclude_whitespace_changes(&mut changed)
+            } else {
+                0
+            };
+            let excluded_marked_lines =
+                diff::pragmas::exclude_marked_lines(&mut changed, &source, &exclude_markers);
+            let moved = match moved_lines_mode {
+                cli::MovedLinesMode::Include => Vec::new(),
+                cli::MovedLinesMode::Exclude | cli::MovedLinesMode::Separate => {
+                    diff::moved::split_moved_lines(&mut changed, moved_min_lines)
+                }
+            };
 
-            let coverage = load_coverage_files(&coverage_files)
+            let mut coverage = load_coverage_files(&coverage_files, &path_maps, &source)
                 .map_err(|err| AppError::usage(format!("Failed to parse coverage files: {err}")))?;
+            if min_hits > 1 {
+                coverage.require_min_hits(min_hits);
+            }
+            // Without branch data every diff would pass at 0/0.
+            if fail_under_branches.is_some() && !coverage.has_branches() {
+                return Err(AppError::usage(
+                    "--fail-under-branches requires coverage reports with branch data",
+                ));
+            }
+            coverage.ambiguity = match ambiguous_paths {
+                cli::AmbiguousPathsMode::Merge | cli::AmbiguousPathsMode::Warn => {
+                    coverage::store::AmbiguityPolicy::Merge
+                }
+                cli::AmbiguousPathsMode::Error => coverage::store::AmbiguityPolicy::Error,
+                cli::AmbiguousPathsMode::PreferClosest => {
+                    coverage::store::AmbiguityPolicy::PreferClosest
+                }
+            };
 
-            let treat_missing_as_uncovered =
-                matches!(missing_coverage, cli::MissingCoverageMode::Uncovered);
-            let report =
-                coverage::analyze_changed_coverage(&changed, &coverage, treat_missing_as_uncovered)
+            let analysis = coverage::AnalysisOptions {
+                treat_missing_as_uncovered: matches!(
+                    missing_coverage,
+                    cli::MissingCoverageMode::Uncovered
+                ),
+                attribute_continuation_lines,
+            };
+            let mut report = coverage::analyze_changed_coverage(&changed, &coverage, analysis)
+                .map_err(|err| AppError::usage(err.to_string()))?;
+            if ambiguous_paths == cli::AmbiguousPathsMode::Warn {
+                for ambiguous in &report.ambiguous_paths {
+                    eprintln!(
+                        "Warning: {} matches several coverage files, merging {}",
+                        ambiguous.path,
+                        ambiguous.matches.join(", ")
+                    );
+                }
+            }
+            if stale_coverage != cli::StaleCoverageMode::Ignore {
+                let head_commit_time = crate::vcs::git::commit_time(&head).ok();
+                report.stale_signals = coverage::stale::detect_stale_coverage(
+                    &changed,
+                    &coverage,
+                    &source,
+                    head_commit_time,
+                )
+                .map_err(|err| AppError::usage(err.to_string()))?;
+                if stale_coverage == cli::StaleCoverageMode::Warn {
+                    for signal in &report.stale_signals {
+                        eprintln!("Warning: coverage may be stale: {signal}");
+                    }
+                }
+            }
+            report.threshold_groups = coverage::thresholds::evaluate_thresholds(
+                &changed,
+                &coverage,
+                &thresholds,
+                analysis,
+            )
+            .map_err(|err| AppError::usage(err.to_string()))?;
+            if let Some(full_diff) = &full_diff {
+                let mut base = load_coverage_files(&base_coverage_files, &path_maps, &source)
+                    .map_err(|err| {
+                        AppError::usage(format!("Failed to parse base coverage files: {err}"))
+                    })?;
+                if min_hits > 1 {
+                    base.require_min_hits(min_hits);
+                }
+                report.regressions =
+                    coverage::regressions::find_regressions(&base, &coverage, full_diff)
+                        .map_err(|err| AppError::usage(err.to_string()))?;
+            }
+            (report.project_lines, report.project_covered) =
+                coverage.totals(|path| match total_scope {
+                    cli::TotalScope::All => true,
+                    cli::TotalScope::Filtered => {
+                        // Coverage keys may be rooted elsewhere; match the globs against the
+                        // repository path they name when there is one.
+                        let path = crate::util::path::existing_suffix(path, &source)
+                            .unwrap_or_else(|| path.to_string());
+                        diff::filter::skip_reason(&path, &include_patterns, &exclude_patterns)
+                            .is_none()
+                    }
+                });
+            report.excluded_whitespace_lines = excluded_whitespace_lines;
+            report.excluded_marked_lines = excluded_marked_lines;
+            report.skipped_files = skipped
+                .into_iter()
+                .map(|(path, reason)| report::SkippedFile { path, reason })
+                .collect();
+            report.moved_lines = moved.iter().map(|file| file.changed_lines.len()).sum();
+            if moved_lines_mode == cli::MovedLinesMode::Separate {
+                let moved_report = coverage::analyze_changed_coverage(&moved, &coverage, analysis)
                     .map_err(|err| AppError::usage(err.to_string()))?;
+                report.moved = Some(moved_report.into());
+            }
+            if let Some(reviewed_head) = &reviewed_head {
+                let since = load_changes_since(reviewed_head, &head, &strip_prefixes)
+                    .map_err(AppError::usage)?;
+                let incremental = diff::interdiff::restrict_to(&changed, &since);
+                let incremental_report =
+                    coverage::analyze_changed_coverage(&incremental, &coverage, analysis)
+                        .map_err(|err| AppError::usage(err.to_string()))?;
+                report.incremental = Some(incremental_report.into());
+            }
             write_reports(&report, &output_plan)?;
 
             if let Some(threshold) = fail_under {
@@ -50,23 +239,56 @@ pub fn run(options: cli::CliOptions) -> Result<(), AppError> {
                     return Err(AppError::fail_under(percent, threshold));
                 }
             }
+            if let Some(threshold) = fail_under_branches {
+                let percent = report.branch_coverage_percent();
+                if percent < threshold {
+                    return Err(AppError::fail_under_branches(percent, threshold));
+                }
+            }
+            if let Some(threshold) = fail_under_total {
+                if report.project_lines == 0 {
+                    return Err(AppError::usage(
+                        "--fail-under-total found no measured lines in the project totals",
+                    ));
+                }
+                let percent = report.project_coverage_percent();
+                if percent < threshold {
+                    return Err(AppError::fail_under_total(percent, threshold));
+                }
+            }
+            let missed: Vec<&report::ThresholdGroup> = report
+                .threshold_groups
+                .iter()
+                .filter(|group| !group.passed())
+                .collect();
+            if !missed.is_empty() {
+                return Err(AppError::thresholds(&missed));
+            }
+            if fail_on_regressions && !report.regressions.is_empty() {
+                return Err(AppError::regressions(report.regressed_lines()));
+            }
+            if stale_coverage == cli::StaleCoverageMode::Fail && !report.stale_signals.is_empty() {
+                return Err(AppError::stale_coverage(report.stale_signals.len()));
+            }
+            if fail_on_uncalled_functions && !report.uncalled_functions.is_empty() {
+                return Err(AppError::uncalled_functions(
+                    report.uncalled_functions.len(),
+                ));
+            }
         }
-        (Some(diff_file), true) => {
-            let changed = load_changed_files(&diff_file).map_err(|err| {
-                AppError::usage(format!(
-                    "Failed to parse diff file {}: {err}",
-                    diff_file.display()
-                ))
-            })?;
+        (false, true) => {
+            let changed = load_changed_files(&changes, &strip_prefixes).map_err(AppError::usage)?;
 
             for file in changed {
                 println!("{}: {:?}", file.path, file.changed_lines);
             }
         }
-        (None, false) => {
-            return Err(AppError::usage("coverage path(s) require --diff-file"));
+        (true, false) => {
+            return Err(AppError::usage(
+                "coverage path(s) require --diff-file, --lines, --old-tree/--new-tree or --base",
+            ));
         }
-        (None, true) => {
+        (true, true) => {
             cli::print_help();
         }
     }
@@ -1,24 +1,52 @@
 use std::path::PathBuf;
 
 use super::error::AppError;
+use super::files::ChangeSource;
 use super::output::OutputPlan;
 
 pub(crate) fn validate_fail_under(
+    flag: &str,
     fail_under: Option<f64>,
-    diff_file: Option<&PathBuf>,
+    changes: &ChangeSource,
     coverage_files: &[PathBuf],
 ) -> Result<(), AppError> {
     let Some(threshold) = fail_under else {
         return Ok(());
     };
 
-    if diff_file.is_none() || coverage_files.is_empty() {
-        return Err(AppError::usage(
-            "--fail-under requires both --diff-file and at least one coverage path",
-        ));
+    if changes.is_empty() || coverage_files.is_empty() {
+        return Err(AppError::usage(format!(
+            "{flag} requires a diff source (--diff-file, --lines, --old-tree/--new-tree or --base) and at least one coverage path"
+        )));
     }
     if !(0.0..=100.0).contains(&threshold) {
-        return Err(AppError::usage("--fail-under must be between 0 and 100"));
+        return Err(AppError::usage(format!("{flag} must be between 0 and 100")));
+    }
+
+    Ok(())
+}
+
+/// Checks that a gate which needs coverage results, such as `--fail-on-uncalled-functions`, has
+/// both a diff source and coverage.
+pub(crate) fn validate_gate(
+    flag: &str,
+    enabled: bool,
+    changes: &ChangeSource,
+    coverage_files: &[PathBuf],
+) -> Result<(), AppError> {
+    if enabled && (changes.is_empty() || coverage_files.is_empty()) {
+        return Err(AppError::usage(format!(
+            "{flag} requires a diff source (--diff-file, --lines, --old-tree/--new-tree or --base) and at least one coverage path"
+        )));
+    }
+
+    Ok(())
+}
+
+/// `--head` only picks the end of a `--base` range, so on its own it would be silently ignored.
+pub(crate) fn validate_head(base: Option<&str>, head: Option<&str>) -> Result<(), AppError> {
+    if head.is_some() && base.is_none() {
+        return Err(AppError::usage("--head requires --base"));
     }
 
     Ok(())
@@ -26,12 +54,12 @@ pub(crate) fn validate_fail_under(
 
 pub(crate) fn validate_output_requirements(
     output_plan: &OutputPlan,
-    diff_file: Option<&PathBuf>,
+    changes: &ChangeSource,
     coverage_files: &[PathBuf],
 ) -> Result<(), AppError> {
-    if output_plan.wants_non_cli() && (diff_file.is_none() || coverage_files.is_empty()) {
+    if output_plan.wants_non_cli() && (changes.is_empty() || coverage_files.is_empty()) {
         return Err(AppError::usage(
-            "--output requires both --diff-file and at least one coverage path",
+            "--output requires a diff source (--diff-file, --lines, --old-tree/--new-tree or --base) and at least one coverage path",
         ));
     }
 
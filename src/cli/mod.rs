@@ -3,6 +3,10 @@ use std::path::PathBuf;
 use std::sync::OnceLock;
 
 use clap::{CommandFactory, FromArgMatches, Parser, ValueEnum, ValueHint};
+use regex::Regex;
+
+use crate::coverage::thresholds::ThresholdRule;
+use crate::util::path::PathMap;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
 #[value(rename_all = "kebab_case")]
@@ -68,6 +72,46 @@ pub enum MissingCoverageMode {
     Ignore,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
+#[value(rename_all = "kebab_case")]
+pub enum AmbiguousPathsMode {
+    Merge,
+    Error,
+    Warn,
+    PreferClosest,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
+#[value(rename_all = "kebab_case")]
+pub enum StaleCoverageMode {
+    Ignore,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
+#[value(rename_all = "kebab_case")]
+pub enum TotalScope {
+    All,
+    Filtered,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
+#[value(rename_all = "kebab_case")]
+pub enum MovedLinesMode {
+    Include,
+    Exclude,
+    Separate,
+}
+
+/// A `--diff-file` value, optionally mapped onto a directory of the coverage tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffFileArg {
+    /// Prefix added to every path in the diff, e.g. a submodule checkout directory.
+    pub prefix: Option<String>,
+    pub path: PathBuf,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct OutputTarget {
     pub format: OutputFormat,
@@ -82,8 +126,102 @@ pub struct OutputTarget {
     arg_required_else_help = true
 )]
 pub struct CliOptions {
-    #[arg(long, value_name = "PATH")]
-    pub diff_file: Option<PathBuf>,
+    #[arg(
+        long = "diff-file",
+        value_name = "[PREFIX=]PATH",
+        help = "Diff, patch, mbox or saved GitHub/GitLab API changes JSON to analyze; repeat to apply a patch series in order. PREFIX= maps a nested repository's paths into the coverage tree; start with = for a path that contains =",
+        action = clap::ArgAction::Append,
+        value_parser = parse_diff_file
+    )]
+    pub diff_files: Vec<DiffFileArg>,
+    #[arg(
+        long = "lines",
+        value_name = "PATH:RANGES",
+        help = "Lines to check instead of a diff, e.g. src/parser.rs:120-180,src/lib.rs:10; can be repeated",
+        action = clap::ArgAction::Append,
+        conflicts_with = "diff_files"
+    )]
+    pub lines: Vec<String>,
+    #[arg(
+        long = "lines-file",
+        value_name = "PATH",
+        help = "JSON file of {path: [ranges]} to check instead of a diff; can be repeated",
+        action = clap::ArgAction::Append,
+        conflicts_with = "diff_files"
+    )]
+    pub lines_files: Vec<PathBuf>,
+    #[arg(
+        long = "old-tree",
+        value_name = "PATH",
+        help = "Base source tree or file to compare against --new-tree instead of reading a diff",
+        requires = "new_tree",
+        conflicts_with_all = ["diff_files", "lines", "lines_files"]
+    )]
+    pub old_tree: Option<PathBuf>,
+    #[arg(
+        long = "new-tree",
+        value_name = "PATH",
+        help = "Head source tree or file whose changed lines are checked",
+        requires = "old_tree"
+    )]
+    pub new_tree: Option<PathBuf>,
+    #[arg(
+        long = "base",
+        value_name = "REV",
+        help = "Compute the diff with local git from the merge base of REV and --head",
+        conflicts_with_all = ["diff_files", "lines", "lines_files", "old_tree"]
+    )]
+    pub base: Option<String>,
+    #[arg(
+        long = "head",
+        value_name = "REV",
+        help = "Head revision for --base and --reviewed-head (default: HEAD)"
+    )]
+    pub head: Option<String>,
+    #[arg(
+        long = "reviewed-head",
+        value_name = "REV",
+        help = "Previously reviewed head; also report coverage of the lines changed since it",
+        requires = "base"
+    )]
+    pub reviewed_head: Option<String>,
+    #[arg(
+        long = "strip-prefix",
+        value_name = "PREFIX",
+        help = "Prefix to strip from diff paths, e.g. a Perforce depot or repository root; can be repeated",
+        action = clap::ArgAction::Append
+    )]
+    pub strip_prefixes: Vec<String>,
+    #[arg(
+        long = "path-map",
+        value_name = "FROM=TO",
+        help = "Rewrite coverage paths starting with FROM to start with TO, e.g. /app= for reports built in a container; can be repeated",
+        action = clap::ArgAction::Append,
+        value_parser = parse_path_map
+    )]
+    pub path_maps: Vec<PathMap>,
+    #[arg(
+        long = "include",
+        value_name = "GLOB",
+        help = "Only check changed files matching this glob; a bare name also matches directories, as in .gitignore; can be repeated",
+        action = clap::ArgAction::Append
+    )]
+    pub include: Vec<String>,
+    #[arg(
+        long = "exclude",
+        value_name = "GLOB",
+        help = "Skip changed files matching this glob, e.g. 'migrations' or '*.pb.go'; can be repeated",
+        action = clap::ArgAction::Append
+    )]
+    pub exclude: Vec<String>,
+    #[arg(
+        long = "exclude-marker",
+        value_name = "REGEX",
+        help = "Skip changed lines matching this regex, in addition to pragma: no cover, LCOV_EXCL_* and @codeCoverageIgnore; can be repeated",
+        action = clap::ArgAction::Append,
+        value_parser = parse_marker
+    )]
+    pub exclude_markers: Vec<Regex>,
     #[arg(
         value_name = "COVERAGE",
         help = "Coverage file or directory; can be repeated or comma-separated",
@@ -94,6 +232,52 @@ pub struct CliOptions {
     pub coverage_paths: Vec<PathBuf>,
     #[arg(long, value_name = "PERCENT")]
     pub fail_under: Option<f64>,
+    #[arg(
+        long = "fail-under-branches",
+        value_name = "PERCENT",
+        help = "Minimum acceptable branch coverage of the changed lines"
+    )]
+    pub fail_under_branches: Option<f64>,
+    #[arg(
+        long = "fail-under-total",
+        value_name = "PERCENT",
+        help = "Minimum acceptable coverage of the whole project"
+    )]
+    pub fail_under_total: Option<f64>,
+    #[arg(
+        long = "total-scope",
+        value_name = "SCOPE",
+        default_value = "all",
+        help = "Files counted in the project totals: all, or filtered by --include/--exclude"
+    )]
+    pub total_scope: TotalScope,
+    #[arg(
+        long = "fail-on-uncalled-functions",
+        help = "Fail when a changed function was never called by the tests"
+    )]
+    pub fail_on_uncalled_functions: bool,
+    #[arg(
+        long = "base-coverage",
+        value_name = "PATH",
+        help = "Coverage file or directory from the target branch, to find lines that lost coverage; can be repeated",
+        action = clap::ArgAction::Append,
+        value_hint = ValueHint::AnyPath
+    )]
+    pub base_coverage: Vec<PathBuf>,
+    #[arg(
+        long = "fail-on-regressions",
+        help = "Fail when a line covered in --base-coverage is no longer covered",
+        requires = "base_coverage"
+    )]
+    pub fail_on_regressions: bool,
+    #[arg(
+        long = "threshold",
+        value_name = "GLOB=PERCENT",
+        help = "Minimum coverage of the changed lines in files matching GLOB; the most specific rule wins; can be repeated",
+        action = clap::ArgAction::Append,
+        value_parser = parse_threshold
+    )]
+    pub thresholds: Vec<ThresholdRule>,
     #[arg(
         long = "missing-coverage",
         value_name = "MODE",
@@ -101,6 +285,52 @@ pub struct CliOptions {
         help = "How to handle files missing from coverage: uncovered or ignore"
     )]
     pub missing_coverage: MissingCoverageMode,
+    #[arg(
+        long = "min-hits",
+        value_name = "N",
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "Count a changed line as covered only if it ran at least N times"
+    )]
+    pub min_hits: u32,
+    #[arg(
+        long = "ambiguous-paths",
+        value_name = "MODE",
+        default_value = "merge",
+        help = "How to handle a changed file matching several coverage files: merge, error, warn or prefer-closest"
+    )]
+    pub ambiguous_paths: AmbiguousPathsMode,
+    #[arg(
+        long = "stale-coverage",
+        value_name = "MODE",
+        default_value = "ignore",
+        help = "What to do when the coverage looks older than the sources: ignore, warn or fail"
+    )]
+    pub stale_coverage: StaleCoverageMode,
+    #[arg(
+        long,
+        help = "Skip added lines that only change whitespace, and blank or comment-only added lines"
+    )]
+    pub ignore_whitespace: bool,
+    #[arg(
+        long = "attribute-continuation-lines",
+        help = "Give unmeasured changed lines the coverage of the nearest measured line above them in the same hunk"
+    )]
+    pub attribute_continuation_lines: bool,
+    #[arg(
+        long = "moved-lines",
+        value_name = "MODE",
+        default_value = "include",
+        help = "How to treat code moved within the diff: include, exclude or separate"
+    )]
+    pub moved_lines: MovedLinesMode,
+    #[arg(
+        long = "moved-min-lines",
+        value_name = "LINES",
+        default_value_t = 3,
+        help = "Minimum number of non-blank lines for a block to count as moved"
+    )]
+    pub moved_min_lines: usize,
     #[arg(
         long = "output",
         id = "output",
@@ -113,6 +343,55 @@ pub struct CliOptions {
     pub outputs: Vec<OutputTarget>,
 }
 
+/// Parses `[PREFIX=]PATH`. A leading `=` means no prefix, so `=a=b.diff` names the file `a=b.diff`.
+fn parse_diff_file(raw: &str) -> Result<DiffFileArg, String> {
+    let (prefix, path) = match raw.split_once('=') {
+        Some(("", path)) => (None, path),
+        Some((prefix, path)) => (Some(prefix.trim_end_matches('/').to_string()), path),
+        None => (None, raw),
+    };
+    if path.is_empty() {
+        return Err("diff file path cannot be empty".to_string());
+    }
+    Ok(DiffFileArg {
+        prefix,
+        path: PathBuf::from(path),
+    })
+}
+
+fn parse_path_map(raw: &str) -> Result<PathMap, String> {
+    match raw.split_once('=') {
+        Some((from, to)) if !from.is_empty() => Ok(PathMap {
+            from: from.replace('\\', "/"),
+            to: to.replace('\\', "/"),
+        }),
+        _ => Err("path map must look like FROM=TO".to_string()),
+    }
+}
+
+fn parse_threshold(raw: &str) -> Result<ThresholdRule, String> {
+    let (pattern, percent) = raw
+        .rsplit_once('=')
+        .filter(|(pattern, _)| !pattern.is_empty())
+        .ok_or_else(|| "threshold must look like GLOB=PERCENT".to_string())?;
+    let percent: f64 = percent
+        .trim()
+        .trim_end_matches('%')
+        .parse()
+        .map_err(|_| format!("invalid threshold percent '{percent}'"))?;
+    if !(0.0..=100.0).contains(&percent) {
+        return Err("threshold percent must be between 0 and 100".to_string());
+    }
+    Ok(ThresholdRule {
+        pattern: pattern.to_string(),
+        percent,
+    })
+}
+
+fn parse_marker(raw: &str) -> Result<Regex, String> {
+    Regex::new(raw).map_err(|err| format!("invalid marker regex: {err}"))
+}
+
 fn parse_output_target(raw: &str) -> Result<OutputTarget, String> {
     let (format_raw, path_raw) = match raw.split_once('=') {
         Some((format_raw, path_raw)) => (format_raw, Some(path_raw)),
@@ -131,9 +410,7 @@ fn parse_output_target(raw: &str) -> Result<OutputTarget, String> {
             "output target must be FORMAT=PATH for formats that require a path: {}",
             OutputFormat::labels_requiring_path().join(", ")
         )),
-        (true, Some(path_raw)) if path_raw.is_empty() => {
-            Err("output target path cannot be empty".to_string())
-        }
+        (true, Some("")) => Err("output target path cannot be empty".to_string()),
         (true, Some(path_raw)) => Ok(OutputTarget {
             format,
             path: Some(PathBuf::from(path_raw)),
@@ -192,7 +469,10 @@ pub fn print_help() {
 mod tests {
     use std::ffi::OsString;
 
-    use super::{parse_args, MissingCoverageMode, OutputFormat};
+    use super::{
+        parse_args, AmbiguousPathsMode, MissingCoverageMode, MovedLinesMode, OutputFormat,
+        StaleCoverageMode, TotalScope,
+    };
 
     #[test]
     fn parses_diff_file_flag() {
@@ -202,7 +482,8 @@ mod tests {
             OsString::from("diff.txt"),
         ])
         .expect("parse");
-        assert_eq!(options.diff_file.unwrap().to_string_lossy(), "diff.txt");
+        assert_eq!(options.diff_files.len(), 1);
+        assert_eq!(options.diff_files[0].path.to_string_lossy(), "diff.txt");
     }
 
     #[test]
@@ -212,7 +493,8 @@ mod tests {
             OsString::from("--diff-file=diff.txt"),
         ])
         .expect("parse");
-        assert_eq!(options.diff_file.unwrap().to_string_lossy(), "diff.txt");
+        assert_eq!(options.diff_files.len(), 1);
+        assert_eq!(options.diff_files[0].path.to_string_lossy(), "diff.txt");
     }
 
     #[test]
@@ -256,6 +538,165 @@ mod tests {
         assert_eq!(options.fail_under, Some(82.5));
     }
 
+    #[test]
+    fn parses_fail_under_branches_flag() {
+        let options = parse_args([
+            OsString::from("bin"),
+            OsString::from("--fail-under-branches=60"),
+        ])
+        .expect("parse");
+        assert_eq!(options.fail_under_branches, Some(60.0));
+    }
+
+    #[test]
+    fn parses_fail_on_uncalled_functions_flag() {
+        let options = parse_args([
+            OsString::from("bin"),
+            OsString::from("--fail-on-uncalled-functions"),
+        ])
+        .expect("parse");
+        assert!(options.fail_on_uncalled_functions);
+    }
+
+    #[test]
+    fn parses_path_maps() {
+        let options = parse_args([
+            OsString::from("bin"),
+            OsString::from("--path-map"),
+            OsString::from("/app="),
+            OsString::from("--path-map=C:\\build=src"),
+        ])
+        .expect("parse");
+        assert_eq!(options.path_maps.len(), 2);
+        assert_eq!(options.path_maps[0].from, "/app");
+        assert_eq!(options.path_maps[0].to, "");
+        assert_eq!(options.path_maps[1].from, "C:/build");
+        assert_eq!(options.path_maps[1].to, "src");
+    }
+
+    #[test]
+    fn parses_ambiguous_paths_mode() {
+        let options = parse_args([
+            OsString::from("bin"),
+            OsString::from("--ambiguous-paths=prefer-closest"),
+        ])
+        .expect("parse");
+        assert_eq!(options.ambiguous_paths, AmbiguousPathsMode::PreferClosest);
+    }
+
+    #[test]
+    fn parses_include_and_exclude_globs() {
+        let options = parse_args([
+            OsString::from("bin"),
+            OsString::from("--include"),
+            OsString::from("src/**"),
+            OsString::from("--exclude=*.pb.go"),
+            OsString::from("--exclude=**/migrations/**"),
+        ])
+        .expect("parse");
+        assert_eq!(options.include, vec!["src/**".to_string()]);
+        assert_eq!(options.exclude.len(), 2);
+    }
+
+    #[test]
+    fn parses_exclude_markers() {
+        let options = parse_args([
+            OsString::from("bin"),
+            OsString::from("--exclude-marker"),
+            OsString::from(r"//\s*nocov"),
+        ])
+        .expect("parse");
+        assert_eq!(options.exclude_markers.len(), 1);
+        assert!(options.exclude_markers[0].is_match("x(); // nocov"));
+    }
+
+    #[test]
+    fn parses_threshold_rules() {
+        let options = parse_args([
+            OsString::from("bin"),
+            OsString::from("--threshold"),
+            OsString::from("src/core/**=90"),
+            OsString::from("--threshold=src/cli/**=60%"),
+        ])
+        .expect("parse");
+        assert_eq!(options.thresholds.len(), 2);
+        assert_eq!(options.thresholds[0].pattern, "src/core/**");
+        assert_eq!(options.thresholds[0].percent, 90.0);
+        assert_eq!(options.thresholds[1].percent, 60.0);
+    }
+
+    #[test]
+    fn parses_stale_coverage_mode() {
+        let options = parse_args([
+            OsString::from("bin"),
+            OsString::from("--diff-file"),
+            OsString::from("diff.txt"),
+        ])
+        .expect("parse");
+        assert_eq!(options.stale_coverage, StaleCoverageMode::Ignore);
+
+        let options = parse_args([
+            OsString::from("bin"),
+            OsString::from("--stale-coverage=fail"),
+        ])
+        .expect("parse");
+        assert_eq!(options.stale_coverage, StaleCoverageMode::Fail);
+    }
+
+    #[test]
+    fn parses_min_hits() {
+        let options = parse_args([
+            OsString::from("bin"),
+            OsString::from("--diff-file"),
+            OsString::from("diff.txt"),
+        ])
+        .expect("parse");
+        assert_eq!(options.min_hits, 1);
+
+        let options =
+            parse_args([OsString::from("bin"), OsString::from("--min-hits=3")]).expect("parse");
+        assert_eq!(options.min_hits, 3);
+    }
+
+    #[test]
+    fn parses_base_coverage() {
+        let options = parse_args([
+            OsString::from("bin"),
+            OsString::from("--base-coverage"),
+            OsString::from("base/coverage.xml"),
+            OsString::from("--fail-on-regressions"),
+        ])
+        .expect("parse");
+        assert_eq!(
+            options.base_coverage[0].to_string_lossy(),
+            "base/coverage.xml"
+        );
+        assert!(options.fail_on_regressions);
+    }
+
+    #[test]
+    fn parses_fail_under_total() {
+        let options = parse_args([
+            OsString::from("bin"),
+            OsString::from("--fail-under-total=75"),
+            OsString::from("--total-scope"),
+            OsString::from("filtered"),
+        ])
+        .expect("parse");
+        assert_eq!(options.fail_under_total, Some(75.0));
+        assert_eq!(options.total_scope, TotalScope::Filtered);
+    }
+
+    #[test]
+    fn parses_attribute_continuation_lines_flag() {
+        let options = parse_args([
+            OsString::from("bin"),
+            OsString::from("--attribute-continuation-lines"),
+        ])
+        .expect("parse");
+        assert!(options.attribute_continuation_lines);
+    }
+
     #[test]
     fn parses_output_target() {
         let options = parse_args([
@@ -326,4 +767,142 @@ mod tests {
         .expect("parse");
         assert_eq!(options.missing_coverage, MissingCoverageMode::Ignore);
     }
+
+    #[test]
+    fn parses_ignore_whitespace_flag() {
+        let options = parse_args([
+            OsString::from("bin"),
+            OsString::from("--diff-file"),
+            OsString::from("diff.txt"),
+            OsString::from("--ignore-whitespace"),
+        ])
+        .expect("parse");
+        assert!(options.ignore_whitespace);
+    }
+
+    #[test]
+    fn parses_moved_lines_options() {
+        let options = parse_args([
+            OsString::from("bin"),
+            OsString::from("--diff-file"),
+            OsString::from("diff.txt"),
+            OsString::from("--moved-lines"),
+            OsString::from("separate"),
+            OsString::from("--moved-min-lines=5"),
+        ])
+        .expect("parse");
+        assert_eq!(options.moved_lines, MovedLinesMode::Separate);
+        assert_eq!(options.moved_min_lines, 5);
+    }
+
+    #[test]
+    fn parses_strip_prefix_flags() {
+        let options = parse_args([
+            OsString::from("bin"),
+            OsString::from("--diff-file"),
+            OsString::from("diff.txt"),
+            OsString::from("--strip-prefix"),
+            OsString::from("//depot/main"),
+            OsString::from("--strip-prefix=trunk"),
+        ])
+        .expect("parse");
+        assert_eq!(options.strip_prefixes, vec!["//depot/main", "trunk"]);
+    }
+
+    #[test]
+    fn parses_repeated_diff_files_in_order() {
+        let options = parse_args([
+            OsString::from("bin"),
+            OsString::from("--diff-file"),
+            OsString::from("0001.patch"),
+            OsString::from("--diff-file=0002.patch"),
+        ])
+        .expect("parse");
+        assert_eq!(options.diff_files.len(), 2);
+        assert_eq!(options.diff_files[0].path.to_string_lossy(), "0001.patch");
+        assert_eq!(options.diff_files[1].path.to_string_lossy(), "0002.patch");
+    }
+
+    #[test]
+    fn parses_line_selection_flags() {
+        let options = parse_args([
+            OsString::from("bin"),
+            OsString::from("--lines"),
+            OsString::from("src/parser.rs:120-180,src/lib.rs:10"),
+            OsString::from("--lines-file=lines.json"),
+        ])
+        .expect("parse");
+        assert_eq!(options.lines, vec!["src/parser.rs:120-180,src/lib.rs:10"]);
+        assert_eq!(options.lines_files.len(), 1);
+        assert_eq!(options.lines_files[0].to_string_lossy(), "lines.json");
+    }
+
+    #[test]
+    fn rejects_lines_with_diff_file() {
+        let result = parse_args([
+            OsString::from("bin"),
+            OsString::from("--diff-file=diff.txt"),
+            OsString::from("--lines=src/lib.rs:10"),
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn parses_tree_flags() {
+        let options = parse_args([
+            OsString::from("bin"),
+            OsString::from("--old-tree"),
+            OsString::from("base"),
+            OsString::from("--new-tree=head"),
+        ])
+        .expect("parse");
+        assert_eq!(options.old_tree.unwrap().to_string_lossy(), "base");
+        assert_eq!(options.new_tree.unwrap().to_string_lossy(), "head");
+    }
+
+    #[test]
+    fn parses_incremental_review_flags() {
+        let options = parse_args([
+            OsString::from("bin"),
+            OsString::from("--base"),
+            OsString::from("origin/main"),
+            OsString::from("--reviewed-head=abc123"),
+        ])
+        .expect("parse");
+        assert_eq!(options.base.as_deref(), Some("origin/main"));
+        assert_eq!(options.head, None);
+        assert_eq!(options.reviewed_head.as_deref(), Some("abc123"));
+    }
+
+    #[test]
+    fn parses_diff_file_prefix_mapping() {
+        let options = parse_args([
+            OsString::from("bin"),
+            OsString::from("--diff-file"),
+            OsString::from("0001.patch"),
+            OsString::from("--diff-file"),
+            OsString::from("libs/core/=core.diff"),
+            OsString::from("--diff-file==a=b.diff"),
+        ])
+        .expect("parse");
+        let files: Vec<(Option<&str>, String)> = options
+            .diff_files
+            .iter()
+            .map(|file| {
+                (
+                    file.prefix.as_deref(),
+                    file.path.to_string_lossy().into_owned(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                (None, "0001.patch".to_string()),
+                (Some("libs/core"), "core.diff".to_string()),
+                (None, "a=b.diff".to_string()),
+            ]
+        );
+        assert!(parse_args([OsString::from("bin"), OsString::from("--diff-file=core=")]).is_err());
+    }
 }
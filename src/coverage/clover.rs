@@ -4,6 +4,7 @@ use std::path::Path;
 use quick_xml::events::Event;
 use quick_xml::Reader;
 
+use super::store::{BranchCounts, FunctionCoverage};
 use super::{CoverageParser, CoverageSink};
 use crate::util::path::normalize_path;
 
@@ -37,6 +38,13 @@ impl CoverageParser for CloverParser {
         loop {
             match xml.read_event_into(&mut buf) {
                 Ok(Event::Start(event)) => match event.name().as_ref() {
+                    b"coverage" => {
+                        if let Some(seconds) = read_attribute_any(&event, &[b"generated"])?
+                            .and_then(|v| super::parse_timestamp(&v))
+                        {
+                            sink.on_generated(seconds);
+                        }
+                    }
                     b"file" => {
                         if let Some(path) =
                             read_attribute_any(&event, &[b"name", b"path", b"filename"])?
@@ -48,8 +56,15 @@ impl CoverageParser for CloverParser {
                     }
                     b"line" => {
                         if let Some(file_path) = current_file.as_deref() {
-                            if let Some((number, hits)) = read_line_attributes(&event)? {
+                            if let Some(function) = read_method_attributes(&event)? {
+                                sink.on_function(file_path, function);
+                            } else if let Some((number, hits, branches)) =
+                                read_line_attributes(&event)?
+                            {
                                 sink.on_line(file_path, number, hits);
+                                if let Some(branches) = branches {
+                                    sink.on_branches(file_path, number, branches);
+                                }
                             }
                         }
                     }
@@ -66,17 +81,22 @@ impl CoverageParser for CloverParser {
                     }
                     b"line" => {
                         if let Some(file_path) = current_file.as_deref() {
-                            if let Some((number, hits)) = read_line_attributes(&event)? {
+                            if let Some(function) = read_method_attributes(&event)? {
+                                sink.on_function(file_path, function);
+                            } else if let Some((number, hits, branches)) =
+                                read_line_attributes(&event)?
+                            {
                                 sink.on_line(file_path, number, hits);
+                                if let Some(branches) = branches {
+                                    sink.on_branches(file_path, number, branches);
+                                }
                             }
                         }
                     }
                     _ => {}
                 },
-                Ok(Event::End(event)) => {
-                    if event.name().as_ref() == b"file" {
-                        current_file = None;
-                    }
+                Ok(Event::End(event)) if event.name().as_ref() == b"file" => {
+                    current_file = None;
                 }
                 Ok(Event::Eof) => break,
                 Err(err) => {
@@ -110,10 +130,36 @@ fn read_attribute_any(
     Ok(None)
 }
 
-fn read_line_attributes(event: &quick_xml::events::BytesStart<'_>) -> Result<Option<(u32, u32)>> {
+/// Reads a `type="method"` line as a function entry point; its end is not recorded by Clover.
+fn read_method_attributes(
+    event: &quick_xml::events::BytesStart<'_>,
+) -> Result<Option<FunctionCoverage>> {
+    if read_attribute_any(event, &[b"type"])?.as_deref() != Some("method") {
+        return Ok(None);
+    }
+    let number = read_attribute_any(event, &[b"num", b"number"])?
+        .as_deref()
+        .and_then(parse_u32_lossy);
+    let hits = read_attribute_any(event, &[b"count", b"hits"])?
+        .as_deref()
+        .and_then(parse_u32_lossy);
+    let name = read_attribute_any(event, &[b"name"])?;
+    Ok(number.map(|start_line| FunctionCoverage {
+        name: name.unwrap_or_else(|| format!("line {start_line}")),
+        start_line,
+        end_line: None,
+        hits: hits.unwrap_or(0),
+    }))
+}
+
+fn read_line_attributes(
+    event: &quick_xml::events::BytesStart<'_>,
+) -> Result<Option<(u32, u32, Option<BranchCounts>)>> {
     let mut number: Option<u32> = None;
     let mut hits: Option<u32> = None;
     let mut line_type: Option<String> = None;
+    let mut true_count: Option<u32> = None;
+    let mut false_count: Option<u32> = None;
 
     for attr in event.attributes() {
         let attr = attr.map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
@@ -136,18 +182,35 @@ fn read_line_attributes(event: &quick_xml::events::BytesStart<'_>) -> Result<Opt
                     .map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
                 line_type = Some(value.into_owned());
             }
+            b"truecount" => {
+                let value = attr
+                    .unescape_value()
+                    .map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+                true_count = parse_u32_lossy(&value);
+            }
+            b"falsecount" => {
+                let value = attr
+                    .unescape_value()
+                    .map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+                false_count = parse_u32_lossy(&value);
+            }
             _ => {}
         }
     }
 
-    if let Some(line_type) = line_type.as_deref() {
-        if line_type == "method" {
-            return Ok(None);
-        }
-    }
+    let branches = match line_type.as_deref() {
+        Some("method") => return Ok(None),
+        // A condition has two outcomes; each one taken at least once counts as covered.
+        Some("cond") => Some(BranchCounts {
+            covered: u32::from(true_count.unwrap_or(0) > 0)
+                + u32::from(false_count.unwrap_or(0) > 0),
+            total: 2,
+        }),
+        _ => None,
+    };
 
     match (number, hits) {
-        (Some(number), Some(hits)) => Ok(Some((number, hits))),
+        (Some(number), Some(hits)) => Ok(Some((number, hits, branches))),
         _ => Ok(None),
     }
 }
@@ -176,7 +239,7 @@ fn parse_u32_lossy(value: &str) -> Option<u32> {
 #[cfg(test)]
 mod tests {
     use super::CloverParser;
-    use crate::coverage::store::CoverageStore;
+    use crate::coverage::store::{BranchCounts, CoverageStore};
     use crate::coverage::CoverageParser;
     use std::io::Cursor;
 
@@ -214,7 +277,14 @@ mod tests {
             .parse(cov_file, &mut store)
             .expect("parse clover");
 
-        let report = crate::coverage::analyze_changed_coverage(&changed, &store, true);
+        let report = crate::coverage::analyze_changed_coverage(
+            &changed,
+            &store,
+            crate::coverage::AnalysisOptions {
+                treat_missing_as_uncovered: true,
+                ..Default::default()
+            },
+        );
         let report = report.expect("report");
         let calc = report
             .uncovered_files
@@ -224,6 +294,27 @@ mod tests {
         assert!(!calc.uncovered_lines.contains(&11));
     }
 
+    #[test]
+    fn records_methods_as_functions() {
+        let file = Cursor::new(include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/coverage_clover.xml"
+        )));
+        let mut store = CoverageStore::default();
+        CloverParser.parse(file, &mut store).expect("parse clover");
+
+        let coverage = store
+            .file_coverage("src/Calculator.php")
+            .expect("lookup")
+            .expect("file coverage");
+        let functions: Vec<_> = coverage
+            .functions
+            .values()
+            .map(|function| (function.name.as_str(), function.start_line, function.hits))
+            .collect();
+        assert_eq!(functions, vec![("sub", 9, 1), ("add", 14, 0)]);
+    }
+
     #[test]
     fn ignores_method_lines() {
         let file = Cursor::new(include_str!(concat!(
@@ -258,4 +349,30 @@ mod tests {
         )));
         assert!(!CloverParser.can_parse(file).expect("detect cobertura"));
     }
+
+    #[test]
+    fn parses_condition_outcomes() {
+        let xml = r#"<coverage><project><file name="src/a.php">
+<line num="5" type="cond" truecount="3" falsecount="0" count="3"/>
+<line num="6" type="stmt" count="3"/>
+</file></project></coverage>"#;
+        let mut store = CoverageStore::default();
+        CloverParser
+            .parse(Cursor::new(xml), &mut store)
+            .expect("parse clover");
+
+        let coverage = store
+            .file_coverage("src/a.php")
+            .expect("lookup")
+            .expect("file coverage");
+        assert!(coverage.is_covered(5));
+        assert_eq!(
+            coverage.branches.get(&5),
+            Some(&BranchCounts {
+                covered: 1,
+                total: 2
+            })
+        );
+        assert!(!coverage.branches.contains_key(&6));
+    }
 }
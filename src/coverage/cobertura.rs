@@ -1,14 +1,25 @@
 use std::io::{BufReader, Error, ErrorKind, Read, Result};
 use std::path::Path;
 
-use super::{CoverageParser, CoverageSink};
+use super::store::{BranchCounts, FunctionCoverage};
+use super::{parse_timestamp, CoverageParser, CoverageSink};
 use crate::util::path::normalize_path;
+use crate::util::source::SourceTree;
 use quick_xml::events::Event;
 use quick_xml::Reader;
 
-pub struct CoberturaParser;
+/// Parses Cobertura XML, resolving class filenames against the files in `source`.
+pub struct CoberturaParser<'a> {
+    source: &'a SourceTree,
+}
 
-impl CoverageParser for CoberturaParser {
+impl<'a> CoberturaParser<'a> {
+    pub fn new(source: &'a SourceTree) -> Self {
+        Self { source }
+    }
+}
+
+impl CoverageParser for CoberturaParser<'_> {
     fn can_parse<R: Read>(&self, reader: R) -> Result<bool> {
         let mut limited = reader.take(8192);
         let mut buf = String::new();
@@ -33,21 +44,46 @@ impl CoverageParser for CoberturaParser {
         xml.config_mut().trim_text(true);
         let mut buf = Vec::new();
         let mut current_file: Option<String> = None;
+        let mut current_method: Option<FunctionCoverage> = None;
+        let mut sources: Vec<String> = Vec::new();
+        let mut in_source = false;
 
         loop {
             match xml.read_event_into(&mut buf) {
                 Ok(Event::Start(event)) => match event.name().as_ref() {
+                    b"coverage" => {
+                        if let Some(seconds) =
+                            read_attribute(&event, b"timestamp")?.and_then(|v| parse_timestamp(&v))
+                        {
+                            sink.on_generated(seconds);
+                        }
+                    }
+                    b"source" => in_source = true,
                     b"class" => {
                         if let Some(path) = read_attribute(&event, b"filename")? {
-                            let normalized = normalize_coverage_path(&path);
+                            let normalized = resolve_class_path(&path, &sources, self.source);
                             current_file = Some(normalized.clone());
                             sink.on_file(&normalized);
                         }
                     }
+                    b"method" => {
+                        current_method = Some(FunctionCoverage {
+                            name: read_attribute(&event, b"name")?.unwrap_or_default(),
+                            start_line: 0,
+                            end_line: None,
+                            hits: 0,
+                        });
+                    }
                     b"line" => {
                         if let Some(file_path) = current_file.as_deref() {
-                            if let Some((number, hits)) = read_line_attributes(&event)? {
+                            if let Some((number, hits, branches)) = read_line_attributes(&event)? {
+                                if let Some(method) = current_method.as_mut() {
+                                    extend_method(method, number, hits);
+                                }
                                 sink.on_line(file_path, number, hits);
+                                if let Some(branches) = branches {
+                                    sink.on_branches(file_path, number, branches);
+                                }
                             }
                         }
                     }
@@ -56,24 +92,49 @@ impl CoverageParser for CoberturaParser {
                 Ok(Event::Empty(event)) => match event.name().as_ref() {
                     b"class" => {
                         if let Some(path) = read_attribute(&event, b"filename")? {
-                            let normalized = normalize_coverage_path(&path);
+                            let normalized = resolve_class_path(&path, &sources, self.source);
                             sink.on_file(&normalized);
                         }
                     }
                     b"line" => {
                         if let Some(file_path) = current_file.as_deref() {
-                            if let Some((number, hits)) = read_line_attributes(&event)? {
+                            if let Some((number, hits, branches)) = read_line_attributes(&event)? {
+                                if let Some(method) = current_method.as_mut() {
+                                    extend_method(method, number, hits);
+                                }
                                 sink.on_line(file_path, number, hits);
+                                if let Some(branches) = branches {
+                                    sink.on_branches(file_path, number, branches);
+                                }
                             }
                         }
                     }
                     _ => {}
                 },
-                Ok(Event::End(event)) => {
-                    if event.name().as_ref() == b"class" {
-                        current_file = None;
+                Ok(Event::Text(text)) if in_source => {
+                    let source = text.unescape().map_err(|err| {
+                        Error::new(
+                            ErrorKind::InvalidData,
+                            format!("Failed to parse Cobertura XML: {err}"),
+                        )
+                    })?;
+                    sources.push(source.trim().to_string());
+                }
+                Ok(Event::End(event)) if event.name().as_ref() == b"source" => {
+                    in_source = false;
+                }
+                Ok(Event::End(event)) if event.name().as_ref() == b"method" => {
+                    if let (Some(file_path), Some(method)) =
+                        (current_file.as_deref(), current_method.take())
+                    {
+                        if method.start_line > 0 {
+                            sink.on_function(file_path, method);
+                        }
                     }
                 }
+                Ok(Event::End(event)) if event.name().as_ref() == b"class" => {
+                    current_file = None;
+                }
                 Ok(Event::Eof) => break,
                 Err(err) => {
                     return Err(Error::new(
@@ -90,6 +151,16 @@ impl CoverageParser for CoberturaParser {
     }
 }
 
+/// Grows a method's span over one of its lines. A method counts as entered when any of its
+/// lines ran, since Cobertura has no separate entry count.
+fn extend_method(method: &mut FunctionCoverage, number: u32, hits: u32) {
+    if method.start_line == 0 || number < method.start_line {
+        method.start_line = number;
+    }
+    method.end_line = method.end_line.max(Some(number));
+    method.hits = method.hits.max(hits);
+}
+
 fn read_attribute(event: &quick_xml::events::BytesStart<'_>, key: &[u8]) -> Result<Option<String>> {
     for attr in event.attributes() {
         let attr = attr.map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
@@ -103,9 +174,12 @@ fn read_attribute(event: &quick_xml::events::BytesStart<'_>, key: &[u8]) -> Resu
     Ok(None)
 }
 
-fn read_line_attributes(event: &quick_xml::events::BytesStart<'_>) -> Result<Option<(u32, u32)>> {
+fn read_line_attributes(
+    event: &quick_xml::events::BytesStart<'_>,
+) -> Result<Option<(u32, u32, Option<BranchCounts>)>> {
     let mut number: Option<u32> = None;
     let mut hits: Option<u32> = None;
+    let mut branches: Option<BranchCounts> = None;
 
     for attr in event.attributes() {
         let attr = attr.map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
@@ -122,16 +196,67 @@ fn read_line_attributes(event: &quick_xml::events::BytesStart<'_>) -> Result<Opt
                     .map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
                 hits = value.parse().ok();
             }
+            b"condition-coverage" => {
+                let value = attr
+                    .unescape_value()
+                    .map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+                branches = parse_condition_coverage(&value);
+            }
             _ => {}
         }
     }
 
     match (number, hits) {
-        (Some(number), Some(hits)) => Ok(Some((number, hits))),
+        (Some(number), Some(hits)) => Ok(Some((number, hits, branches))),
         _ => Ok(None),
     }
 }
 
+/// Parses `50% (1/2)` into covered and total branch counts.
+fn parse_condition_coverage(value: &str) -> Option<BranchCounts> {
+    let inner = value.split_once('(')?.1.split_once(')')?.0;
+    let (covered, total) = inner.split_once('/')?;
+    let counts = BranchCounts {
+        covered: covered.trim().parse().ok()?,
+        total: total.trim().parse().ok()?,
+    };
+    (counts.total > 0).then_some(counts)
+}
+
+/// Resolves a class `filename` against the `<source>` roots so it names a file in `source`. Roots
+/// recorded on another machine are tried by their trailing directories, so `/builds/app/src`
+/// still finds `src/`. Falls back to the filename as written.
+fn resolve_class_path(filename: &str, sources: &[String], source: &SourceTree) -> String {
+    resolve_against_sources(filename, sources, source)
+        .unwrap_or_else(|| normalize_coverage_path(filename))
+}
+
+fn resolve_against_sources(
+    filename: &str,
+    sources: &[String],
+    tree: &SourceTree,
+) -> Option<String> {
+    let filename = normalize_path(filename);
+    if sources.is_empty() || Path::new(&filename).is_absolute() {
+        return None;
+    }
+    sources.iter().find_map(|source| {
+        let source = normalize_path(source);
+        let components: Vec<&str> = source
+            .split('/')
+            .filter(|component| !component.is_empty() && *component != ".")
+            .collect();
+        (0..=components.len()).find_map(|start| {
+            let mut candidate = components[start..].join("/");
+            if !candidate.is_empty() {
+                candidate.push('/');
+            }
+            candidate.push_str(&filename);
+            tree.is_file(&candidate).then_some(candidate)
+        })
+    })
+}
+
 fn normalize_coverage_path(path: &str) -> String {
     let path_obj = Path::new(path);
     if path_obj.is_absolute() {
@@ -146,9 +271,10 @@ fn normalize_coverage_path(path: &str) -> String {
 
 #[cfg(test)]
 mod tests {
-    use super::CoberturaParser;
-    use crate::coverage::store::CoverageStore;
+    use super::{resolve_against_sources, CoberturaParser};
+    use crate::coverage::store::{BranchCounts, CoverageStore};
     use crate::coverage::CoverageParser;
+    use crate::util::source::SourceTree;
     use std::io::Cursor;
 
     #[test]
@@ -157,7 +283,9 @@ mod tests {
             env!("CARGO_MANIFEST_DIR"),
             "/tests/fixtures/coverage_cobertura.xml"
         )));
-        assert!(CoberturaParser.can_parse(file).expect("detect cobertura"));
+        assert!(CoberturaParser::new(&SourceTree::default())
+            .can_parse(file)
+            .expect("detect cobertura"));
     }
 
     #[test]
@@ -166,7 +294,9 @@ mod tests {
             env!("CARGO_MANIFEST_DIR"),
             "/tests/fixtures/coverage_clover.xml"
         )));
-        assert!(!CoberturaParser.can_parse(file).expect("detect clover"));
+        assert!(!CoberturaParser::new(&SourceTree::default())
+            .can_parse(file)
+            .expect("detect clover"));
     }
 
     #[test]
@@ -176,7 +306,7 @@ mod tests {
             "/tests/fixtures/coverage_cobertura.xml"
         )));
         let mut store = CoverageStore::default();
-        CoberturaParser
+        CoberturaParser::new(&SourceTree::default())
             .parse(file, &mut store)
             .expect("parse cobertura");
 
@@ -186,5 +316,57 @@ mod tests {
             .expect("file coverage");
         assert_eq!(coverage.measured_lines, vec![11, 16]);
         assert!(coverage.covered_lines.is_empty());
+        let functions: Vec<_> = coverage
+            .functions
+            .values()
+            .map(|function| (function.name.as_str(), function.end_line, function.hits))
+            .collect();
+        assert_eq!(functions, vec![("sub", Some(11), 0), ("add", Some(16), 0)]);
+    }
+
+    #[test]
+    fn resolves_filenames_against_source_roots() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("app/src")).expect("create dirs");
+        std::fs::write(root.join("app/src/Calculator.php"), "<?php\n").expect("write source");
+
+        let tree = SourceTree::Directory(root.to_path_buf());
+        let sources = vec!["/builds/group/project/app/src".to_string()];
+        assert_eq!(
+            resolve_against_sources("Calculator.php", &sources, &tree).as_deref(),
+            Some("app/src/Calculator.php")
+        );
+        assert_eq!(
+            resolve_against_sources("Missing.php", &sources, &tree),
+            None
+        );
+    }
+
+    #[test]
+    fn parses_condition_coverage() {
+        let xml = r#"<coverage><packages><package><classes>
+<class filename="src/a.py"><lines>
+<line number="3" hits="1" branch="true" condition-coverage="50% (1/2)"/>
+<line number="4" hits="1"/>
+</lines></class>
+</classes></package></packages></coverage>"#;
+        let mut store = CoverageStore::default();
+        CoberturaParser::new(&SourceTree::default())
+            .parse(Cursor::new(xml), &mut store)
+            .expect("parse cobertura");
+
+        let coverage = store
+            .file_coverage("src/a.py")
+            .expect("lookup")
+            .expect("file coverage");
+        assert_eq!(
+            coverage.branches.get(&3),
+            Some(&BranchCounts {
+                covered: 1,
+                total: 2
+            })
+        );
+        assert!(!coverage.branches.contains_key(&4));
     }
 }
@@ -1,13 +1,20 @@
 pub mod clover;
 pub mod cobertura;
+pub mod regressions;
+pub mod stale;
 pub mod store;
+pub mod thresholds;
 
 use std::io::Read;
 
 use std::collections::BTreeSet;
 
 use crate::diff::types::ChangedFile;
-use crate::report::{CoverageReport, UncoveredFile};
+use crate::diff::types::Hunk;
+use crate::report::{
+    AmbiguousPath, CoverageReport, LowHitLine, PartialBranchFile, PartialBranchLine,
+    UncalledFunction, UncoveredFile, UncoveredHunk,
+};
 use crate::util::path::normalize_path;
 use store::CoverageStore;
 
@@ -19,16 +26,47 @@ pub trait CoverageParser {
 pub trait CoverageSink {
     fn on_file(&mut self, file_path: &str);
     fn on_line(&mut self, file_path: &str, line: u32, hits: u32);
+    fn on_branches(&mut self, file_path: &str, line: u32, counts: store::BranchCounts);
+    fn on_function(&mut self, file_path: &str, function: store::FunctionCoverage);
+    /// The report's generation time, in seconds since the Unix epoch.
+    fn on_generated(&mut self, unix_seconds: u64);
+}
+
+/// Reads a report timestamp as Unix seconds. coverage.py writes milliseconds, so values too
+/// large to be seconds are scaled down.
+fn parse_timestamp(value: &str) -> Option<u64> {
+    let value: u64 = value.trim().parse().ok()?;
+    Some(if value >= 100_000_000_000 {
+        value / 1000
+    } else {
+        value
+    })
+}
+
+/// How changed lines without their own coverage data are counted.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AnalysisOptions {
+    /// Count every changed line of a file missing from coverage as uncovered.
+    pub treat_missing_as_uncovered: bool,
+    /// Give unmeasured changed lines the state of the nearest measured line above them in the
+    /// same hunk, e.g. the continuation lines of a multi-line call.
+    pub attribute_continuation_lines: bool,
 }
 
 pub fn analyze_changed_coverage(
     changed_files: &[ChangedFile],
     coverage: &CoverageStore,
-    treat_missing_as_uncovered: bool,
+    options: AnalysisOptions,
 ) -> Result<CoverageReport, store::CoverageLookupError> {
     let mut uncovered_files = Vec::new();
+    let mut partial_branch_files = Vec::new();
     let mut total_changed = 0usize;
     let mut total_covered = 0usize;
+    let mut total_branches = 0usize;
+    let mut covered_branches = 0usize;
+    let mut uncalled_functions = Vec::new();
+    let mut ambiguous_paths = Vec::new();
+    let mut attributed_lines = 0usize;
 
     for changed_file in changed_files {
         let normalized_path = normalize_path(&changed_file.path);
@@ -37,38 +75,97 @@ pub fn analyze_changed_coverage(
             continue;
         }
 
-        let file_coverage = coverage.file_coverage(&normalized_path)?;
+        let (file_coverage, ambiguity) = coverage.lookup(&normalized_path)?;
+        if let Some(ambiguity) = ambiguity {
+            ambiguous_paths.push(AmbiguousPath {
+                path: normalized_path.clone(),
+                matches: ambiguity.matches,
+                used: ambiguity.used,
+            });
+        }
         let mut uncovered_lines = Vec::new();
+        let mut partial_branches = Vec::new();
+        let mut low_hit_lines = Vec::new();
         let mut covered_count = 0usize;
         let mut changed_count = 0usize;
 
         if let Some(file_coverage) = file_coverage.as_deref() {
-            for line in unique_lines {
-                if !file_coverage.is_measured(line) {
+            let lines: Vec<u32> = unique_lines.iter().copied().collect();
+            uncalled_functions.extend(
+                file_coverage
+                    .functions_touching(&lines)
+                    .into_iter()
+                    .filter(|function| function.hits == 0)
+                    .map(|function| UncalledFunction {
+                        path: normalized_path.clone(),
+                        name: function.name.clone(),
+                        start_line: function.start_line,
+                    }),
+            );
+            for &line in &unique_lines {
+                let measured_line = if file_coverage.is_measured(line) {
+                    line
+                } else if let Some(statement) = options
+                    .attribute_continuation_lines
+                    .then(|| statement_line(file_coverage, changed_file, &unique_lines, line))
+                    .flatten()
+                {
+                    attributed_lines += 1;
+                    statement
+                } else {
                     continue;
+                };
+                // Branches belong to the statement's own line and are counted only there.
+                let branches = file_coverage
+                    .branches
+                    .get(&line)
+                    .filter(|_| measured_line == line);
+                if let Some(branches) = branches {
+                    total_branches += branches.total as usize;
+                    covered_branches += branches.covered.min(branches.total) as usize;
                 }
-                if file_coverage.is_covered(line) {
+                if file_coverage.is_covered(measured_line) {
                     covered_count += 1;
+                    if let Some(branches) = branches.filter(|b| b.covered < b.total) {
+                        partial_branches.push(PartialBranchLine {
+                            line,
+                            covered: branches.covered,
+                            total: branches.total,
+                        });
+                    }
                 } else {
                     uncovered_lines.push(line);
+                    let hits = file_coverage.hits(measured_line);
+                    if hits > 0 {
+                        low_hit_lines.push(LowHitLine { line, hits });
+                    }
                 }
             }
 
             changed_count = covered_count + uncovered_lines.len();
             total_changed += changed_count;
             total_covered += covered_count;
-        } else if treat_missing_as_uncovered {
-            uncovered_lines.extend(unique_lines.into_iter());
+        } else if options.treat_missing_as_uncovered {
+            uncovered_lines.extend(unique_lines);
             changed_count = uncovered_lines.len();
             total_changed += changed_count;
         }
 
+        if !partial_branches.is_empty() {
+            partial_branch_files.push(PartialBranchFile {
+                path: normalized_path.clone(),
+                lines: partial_branches,
+            });
+        }
         if !uncovered_lines.is_empty() {
+            let hunks = uncovered_hunks(&changed_file.hunks, &uncovered_lines);
             uncovered_files.push(UncoveredFile {
                 path: normalized_path,
                 uncovered_lines,
                 covered_lines: covered_count,
                 changed_lines: changed_count,
+                hunks,
+                low_hit_lines,
             });
         }
     }
@@ -77,28 +174,90 @@ pub fn analyze_changed_coverage(
         total_changed,
         total_covered,
         uncovered_files,
+        total_branches,
+        covered_branches,
+        partial_branches: partial_branch_files,
+        uncalled_functions,
+        ambiguous_paths,
+        attributed_lines,
+        ..CoverageReport::default()
     })
 }
 
+/// The nearest measured line above an unmeasured `line` within its hunk. Without hunks, as with
+/// `--lines`, the run of consecutive changed lines stands in for the hunk.
+fn statement_line(
+    coverage: &store::FileCoverage,
+    changed_file: &ChangedFile,
+    changed_lines: &BTreeSet<u32>,
+    line: u32,
+) -> Option<u32> {
+    let first = match changed_file
+        .hunks
+        .iter()
+        .find(|hunk| hunk.contains_new_line(line))
+    {
+        Some(hunk) => hunk.new_start,
+        None if changed_file.hunks.is_empty() => {
+            let mut first = line;
+            while first > 1 && changed_lines.contains(&(first - 1)) {
+                first -= 1;
+            }
+            first
+        }
+        None => return None,
+    };
+    (first..line)
+        .rev()
+        .find(|candidate| coverage.is_measured(*candidate))
+}
+
+fn uncovered_hunks(hunks: &[Hunk], uncovered_lines: &[u32]) -> Vec<UncoveredHunk> {
+    hunks
+        .iter()
+        .filter_map(|hunk| {
+            let lines: Vec<u32> = uncovered_lines
+                .iter()
+                .copied()
+                .filter(|line| hunk.contains_new_line(*line))
+                .collect();
+            if lines.is_empty() {
+                return None;
+            }
+            Some(UncoveredHunk {
+                new_start: hunk.new_start,
+                new_end: hunk.new_end(),
+                section: hunk.section.clone(),
+                uncovered_lines: lines,
+            })
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
-    use super::analyze_changed_coverage;
+    use super::{analyze_changed_coverage, AnalysisOptions};
     use crate::coverage::store::CoverageStore;
     use crate::coverage::CoverageSink;
-    use crate::diff::types::ChangedFile;
+    use crate::diff::types::{ChangedFile, DiffLine, Hunk};
+
+    fn missing_uncovered() -> AnalysisOptions {
+        AnalysisOptions {
+            treat_missing_as_uncovered: true,
+            ..AnalysisOptions::default()
+        }
+    }
 
     #[test]
     fn counts_unique_changed_lines_and_tracks_uncovered() {
-        let changed_files = vec![ChangedFile {
-            path: "src\\foo.rs".to_string(),
-            changed_lines: vec![1, 1, 2, 3],
-        }];
+        let changed_files = vec![ChangedFile::new("src\\foo.rs", vec![1, 1, 2, 3])];
 
         let mut store = CoverageStore::default();
         store.on_line("src/foo.rs", 1, 2);
         store.on_line("src/foo.rs", 2, 0);
 
-        let report = analyze_changed_coverage(&changed_files, &store, true).expect("report");
+        let report =
+            analyze_changed_coverage(&changed_files, &store, missing_uncovered()).expect("report");
 
         assert_eq!(report.total_changed, 2);
         assert_eq!(report.total_covered, 1);
@@ -110,13 +269,11 @@ mod tests {
 
     #[test]
     fn treats_missing_files_as_uncovered() {
-        let changed_files = vec![ChangedFile {
-            path: "src/foo.rs".to_string(),
-            changed_lines: vec![1, 2, 2],
-        }];
+        let changed_files = vec![ChangedFile::new("src/foo.rs", vec![1, 2, 2])];
 
         let store = CoverageStore::default();
-        let report = analyze_changed_coverage(&changed_files, &store, true).expect("report");
+        let report =
+            analyze_changed_coverage(&changed_files, &store, missing_uncovered()).expect("report");
 
         assert_eq!(report.total_changed, 2);
         assert_eq!(report.total_covered, 0);
@@ -128,16 +285,139 @@ mod tests {
 
     #[test]
     fn ignores_missing_files_when_disabled() {
-        let changed_files = vec![ChangedFile {
-            path: "src/foo.rs".to_string(),
-            changed_lines: vec![1, 2, 2],
-        }];
+        let changed_files = vec![ChangedFile::new("src/foo.rs", vec![1, 2, 2])];
 
         let store = CoverageStore::default();
-        let report = analyze_changed_coverage(&changed_files, &store, false).expect("report");
+        let report = analyze_changed_coverage(&changed_files, &store, AnalysisOptions::default())
+            .expect("report");
 
         assert_eq!(report.total_changed, 0);
         assert_eq!(report.total_covered, 0);
         assert!(report.uncovered_files.is_empty());
     }
+
+    #[test]
+    fn groups_uncovered_lines_by_hunk() {
+        let added = |number: u32| DiffLine {
+            number,
+            content: String::new(),
+        };
+        let mut changed = ChangedFile::new("src/foo.rs", vec![2, 10, 11]);
+        changed.hunks = vec![
+            Hunk {
+                old_start: 1,
+                old_count: 2,
+                new_start: 1,
+                new_count: 3,
+                section: None,
+                added: vec![added(2)],
+                removed: Vec::new(),
+            },
+            Hunk {
+                old_start: 8,
+                old_count: 2,
+                new_start: 9,
+                new_count: 4,
+                section: Some("fn bar()".to_string()),
+                added: vec![added(10), added(11)],
+                removed: Vec::new(),
+            },
+        ];
+
+        let mut store = CoverageStore::default();
+        store.on_line("src/foo.rs", 2, 1);
+        store.on_line("src/foo.rs", 10, 0);
+        store.on_line("src/foo.rs", 11, 0);
+
+        let report =
+            analyze_changed_coverage(&[changed], &store, missing_uncovered()).expect("report");
+        let hunks = &report.uncovered_files[0].hunks;
+        assert_eq!(hunks.len(), 1);
+        assert_eq!((hunks[0].new_start, hunks[0].new_end), (9, 12));
+        assert_eq!(hunks[0].section.as_deref(), Some("fn bar()"));
+        assert_eq!(hunks[0].uncovered_lines, vec![10, 11]);
+    }
+
+    #[test]
+    fn counts_branches_and_flags_partially_covered_lines() {
+        use crate::coverage::store::BranchCounts;
+
+        let changed_files = vec![ChangedFile::new("src/foo.rs", vec![1, 2, 3])];
+        let mut store = CoverageStore::default();
+        store.on_line("src/foo.rs", 1, 1);
+        store.on_branches(
+            "src/foo.rs",
+            1,
+            BranchCounts {
+                covered: 1,
+                total: 2,
+            },
+        );
+        store.on_line("src/foo.rs", 2, 1);
+        store.on_branches(
+            "src/foo.rs",
+            2,
+            BranchCounts {
+                covered: 2,
+                total: 2,
+            },
+        );
+        store.on_line("src/foo.rs", 3, 1);
+
+        let report =
+            analyze_changed_coverage(&changed_files, &store, missing_uncovered()).expect("report");
+
+        assert_eq!(report.total_covered, 3);
+        assert_eq!((report.covered_branches, report.total_branches), (3, 4));
+        assert!(report.uncovered_files.is_empty());
+        assert_eq!(report.partial_branches[0].path, "src/foo.rs");
+        assert_eq!(report.partial_branches[0].lines.len(), 1);
+        assert_eq!(report.partial_branches[0].lines[0].line, 1);
+    }
+
+    #[test]
+    fn attributes_continuation_lines_to_their_statement() {
+        let mut changed = ChangedFile::new("src/call.rs", vec![10, 11, 12, 20, 21]);
+        changed.hunks = vec![
+            Hunk {
+                old_start: 10,
+                old_count: 0,
+                new_start: 10,
+                new_count: 3,
+                section: None,
+                added: Vec::new(),
+                removed: Vec::new(),
+            },
+            Hunk {
+                old_start: 17,
+                old_count: 0,
+                new_start: 20,
+                new_count: 2,
+                section: None,
+                added: Vec::new(),
+                removed: Vec::new(),
+            },
+        ];
+
+        let mut store = CoverageStore::default();
+        store.on_line("src/call.rs", 10, 0);
+        // Line 19 is outside the second hunk, so 20 and 21 stay unmeasured.
+        store.on_line("src/call.rs", 19, 1);
+        store.prepare_lookup();
+
+        let options = AnalysisOptions {
+            attribute_continuation_lines: true,
+            ..AnalysisOptions::default()
+        };
+        let report = analyze_changed_coverage(std::slice::from_ref(&changed), &store, options)
+            .expect("report");
+        assert_eq!(report.total_changed, 3);
+        assert_eq!(report.attributed_lines, 2);
+        assert_eq!(report.uncovered_files[0].uncovered_lines, vec![10, 11, 12]);
+
+        let report = analyze_changed_coverage(&[changed], &store, AnalysisOptions::default())
+            .expect("report");
+        assert_eq!(report.total_changed, 1);
+        assert_eq!(report.attributed_lines, 0);
+    }
 }
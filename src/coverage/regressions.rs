@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+
+use super::store::{same_trailing_components, CoverageLookupError, CoverageStore};
+use crate::diff::series::map_line;
+use crate::diff::types::ChangedFile;
+use crate::report::RegressedFile;
+
+/// Finds lines covered in the `base` report that are measured but no longer covered in `head`.
+/// Base line numbers are carried through `diff` onto the head revision, so files outside the
+/// diff are checked too; lines the diff removes are ignored.
+pub fn find_regressions(
+    base: &CoverageStore,
+    head: &CoverageStore,
+    diff: &[ChangedFile],
+) -> Result<Vec<RegressedFile>, CoverageLookupError> {
+    let mut by_name: HashMap<&str, Vec<&ChangedFile>> = HashMap::new();
+    for file in diff {
+        let old_path = file.old_path.as_deref().unwrap_or(&file.path);
+        by_name.entry(file_name(old_path)).or_default().push(file);
+    }
+
+    let mut keys: Vec<&String> = base.files.keys().collect();
+    keys.sort();
+    let mut regressions = Vec::new();
+    for key in keys {
+        let changed = by_name.get(file_name(key)).and_then(|files| {
+            files.iter().find(|file| {
+                same_trailing_components(file.old_path.as_deref().unwrap_or(&file.path), key)
+            })
+        });
+        let head_path = changed.map_or(key.as_str(), |file| file.path.as_str());
+        let Some(head_coverage) = head.file_coverage(head_path)? else {
+            continue;
+        };
+        let Some(base_coverage) = base.file_coverage(key)? else {
+            continue;
+        };
+
+        let mut lines: Vec<u32> = base_coverage
+            .covered_lines
+            .iter()
+            .filter_map(|line| match changed {
+                Some(file) => map_line(&file.hunks, *line),
+                None => Some(*line),
+            })
+            .filter(|line| head_coverage.is_measured(*line) && !head_coverage.is_covered(*line))
+            .collect();
+        lines.sort_unstable();
+        lines.dedup();
+        if !lines.is_empty() {
+            regressions.push(RegressedFile {
+                path: head_path.to_string(),
+                lines,
+            });
+        }
+    }
+    Ok(regressions)
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::find_regressions;
+    use crate::coverage::store::CoverageStore;
+    use crate::coverage::CoverageSink;
+    use crate::diff::git::{parse_unified_diff, parse_unified_patch};
+    use crate::diff::series::{combine_patches, map_line};
+    use std::io::Cursor;
+
+    #[test]
+    fn maps_base_lines_through_the_diff() {
+        let mut base = CoverageStore::default();
+        for (line, hits) in [(1, 1), (2, 1), (3, 1), (4, 1)] {
+            base.on_line("src/lib.rs", line, hits);
+        }
+        base.on_line("src/other.rs", 7, 3);
+        base.prepare_lookup();
+
+        // Two lines are inserted after line 1 and line 3 is removed.
+        let diff = parse_unified_diff(Cursor::new(
+            "\
+diff --git a/src/lib.rs b/src/lib.rs
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,4 +1,5 @@
+ one
++new_a
++new_b
+ two
+-three
+ four
+",
+        ))
+        .expect("parse diff");
+
+        let mut head = CoverageStore::default();
+        for (line, hits) in [(1, 1), (2, 0), (3, 0), (4, 1), (5, 0)] {
+            head.on_line("src/lib.rs", line, hits);
+        }
+        head.on_line("src/other.rs", 7, 0);
+        head.prepare_lookup();
+
+        let regressions = find_regressions(&base, &head, &diff).expect("regressions");
+        assert_eq!(regressions.len(), 2);
+        assert_eq!(regressions[0].path, "src/lib.rs");
+        // Old line 4 is new line 5; new lines 2 and 3 were not covered in base.
+        assert_eq!(regressions[0].lines, vec![5]);
+        assert_eq!(regressions[1].path, "src/other.rs");
+        assert_eq!(regressions[1].lines, vec![7]);
+    }
+
+    #[test]
+    fn maps_base_lines_through_a_patch_series() {
+        // Base: a, x, y, tail, w. The first patch replaces x, the second removes y and adds z.
+        let first = parse_unified_patch(Cursor::new(
+            "\
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -2 +2,2 @@
+-x
++X1
++X2
+",
+        ))
+        .expect("parse diff");
+        let second = parse_unified_patch(Cursor::new(
+            "\
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -4,2 +4,2 @@
+-y
+ tail
++z
+",
+        ))
+        .expect("parse diff");
+        let diff = combine_patches(vec![first, second]);
+
+        let mapped: Vec<Option<u32>> = (1..=5).map(|line| map_line(&diff[0].hunks, line)).collect();
+        assert_eq!(mapped, vec![Some(1), None, None, Some(4), Some(6)]);
+
+        let mut base = CoverageStore::default();
+        let mut head = CoverageStore::default();
+        for line in 1..=5 {
+            base.on_line("src/lib.rs", line, 1);
+        }
+        for line in 1..=6 {
+            head.on_line("src/lib.rs", line, 0);
+        }
+        base.prepare_lookup();
+        head.prepare_lookup();
+
+        let regressions = find_regressions(&base, &head, &diff).expect("regressions");
+        assert_eq!(regressions.len(), 1);
+        assert_eq!(regressions[0].lines, vec![1, 4, 6]);
+    }
+}
@@ -0,0 +1,125 @@
+use super::store::{CoverageLookupError, CoverageStore};
+use crate::diff::types::ChangedFile;
+use crate::report::StaleSignal;
+use crate::util::source::SourceTree;
+
+/// Looks for signs that the coverage reports were produced from older sources: measured lines
+/// past the end of a changed file, measured lines that are blank in the source, and reports generated
+/// before `head_commit_time`. Files are read from `source`; files that cannot be read are left
+/// alone.
+pub fn detect_stale_coverage(
+    changed_files: &[ChangedFile],
+    coverage: &CoverageStore,
+    source: &SourceTree,
+    head_commit_time: Option<u64>,
+) -> Result<Vec<StaleSignal>, CoverageLookupError> {
+    let mut signals = Vec::new();
+
+    if let (Some(generated), Some(committed)) = (coverage.generated_at, head_commit_time) {
+        if generated < committed {
+            signals.push(StaleSignal {
+                path: None,
+                reason: format!(
+                    "coverage was generated {} before the HEAD commit",
+                    describe_age(committed - generated)
+                ),
+            });
+        }
+    }
+
+    for file in changed_files {
+        let Some(file_coverage) = coverage.file_coverage(&file.path)? else {
+            continue;
+        };
+        let Some(text) = source.read_to_string(&file.path) else {
+            continue;
+        };
+        let lines: Vec<&str> = text.lines().collect();
+
+        if let Some(&last) = file_coverage.measured_lines.iter().max() {
+            if last as usize > lines.len() {
+                signals.push(StaleSignal {
+                    path: Some(file.path.clone()),
+                    reason: format!(
+                        "line {last} is measured but the file has {} lines",
+                        lines.len()
+                    ),
+                });
+            }
+        }
+
+        let mut blank: Vec<u32> = file_coverage
+            .measured_lines
+            .iter()
+            .copied()
+            .filter(|&line| {
+                lines
+                    .get((line as usize).wrapping_sub(1))
+                    .is_some_and(|text| text.trim().is_empty())
+            })
+            .collect();
+        blank.sort_unstable();
+        blank.dedup();
+        if !blank.is_empty() {
+            signals.push(StaleSignal {
+                path: Some(file.path.clone()),
+                reason: format!("{} measured line(s) are blank in the source", blank.len()),
+            });
+        }
+    }
+
+    Ok(signals)
+}
+
+fn describe_age(seconds: u64) -> String {
+    match seconds {
+        0..=3599 => format!("{} minute(s)", seconds / 60),
+        3600..=86_399 => format!("{} hour(s)", seconds / 3600),
+        _ => format!("{} day(s)", seconds / 86_400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::detect_stale_coverage;
+    use crate::coverage::store::CoverageStore;
+    use crate::coverage::CoverageSink;
+    use crate::diff::types::ChangedFile;
+    use crate::util::source::SourceTree;
+
+    #[test]
+    fn flags_lines_past_the_end_blank_lines_and_old_reports() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        let root = dir.path();
+        std::fs::write(root.join("lib.rs"), "fn a() {\n\n    b();\n}\n").expect("write source");
+        std::fs::write(root.join("main.rs"), "fn main() {\n    run();\n}\n").expect("write source");
+
+        let mut store = CoverageStore::default();
+        store.on_line("lib.rs", 2, 1);
+        store.on_line("lib.rs", 9, 0);
+        store.on_line("main.rs", 2, 1);
+        store.on_generated(1_000);
+        store.prepare_lookup();
+
+        let changed = vec![
+            ChangedFile::new("lib.rs", vec![3]),
+            ChangedFile::new("main.rs", vec![2]),
+        ];
+        let source = SourceTree::Directory(root.to_path_buf());
+        let signals = detect_stale_coverage(&changed, &store, &source, Some(1_000 + 2 * 3600))
+            .expect("detect");
+        let messages: Vec<String> = signals.iter().map(ToString::to_string).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "coverage was generated 2 hour(s) before the HEAD commit",
+                "lib.rs: line 9 is measured but the file has 4 lines",
+                "lib.rs: 1 measured line(s) are blank in the source",
+            ]
+        );
+        let fresh =
+            detect_stale_coverage(&changed[1..], &store, &source, Some(500)).expect("detect");
+        assert!(fresh.is_empty());
+    }
+}
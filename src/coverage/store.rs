@@ -1,5 +1,6 @@
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 
 use super::CoverageSink;
@@ -8,15 +9,65 @@ use super::CoverageSink;
 pub struct CoverageStore {
     pub files: HashMap<String, FileCoverage>,
     normalized_ready: bool,
+    /// Keys grouped by file name, built by `prepare_lookup`.
+    keys_by_name: HashMap<String, Vec<String>>,
+    /// What to do when a changed file matches several coverage files.
+    pub ambiguity: AmbiguityPolicy,
+    /// When the oldest loaded report was generated, in Unix seconds, if any report says.
+    pub generated_at: Option<u64>,
+}
+
+/// A file's coverage and, when several coverage files matched it, how it was resolved.
+pub type Lookup<'a> = (Option<Cow<'a, FileCoverage>>, Option<Ambiguity>);
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AmbiguityPolicy {
+    /// Combine every matching file.
+    #[default]
+    Merge,
+    /// Fail the lookup.
+    Error,
+    /// Use the matches sharing the most trailing path components with the changed file.
+    PreferClosest,
+}
+
+/// Coverage files that matched one changed file, and the ones that were used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ambiguity {
+    pub matches: Vec<String>,
+    pub used: Vec<String>,
 }
 
 #[derive(Default, Clone, Debug)]
 pub struct FileCoverage {
     pub measured_lines: Vec<u32>,
     pub covered_lines: Vec<u32>,
+    /// Hit count of each measured line, summed across the reports that were added together.
+    pub hits: BTreeMap<u32, u32>,
+    /// Branch counts of lines that have conditions.
+    pub branches: BTreeMap<u32, BranchCounts>,
+    /// Functions keyed by their first line.
+    pub functions: BTreeMap<u32, FunctionCoverage>,
     dirty: bool,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FunctionCoverage {
+    pub name: String,
+    pub start_line: u32,
+    /// Last line of the body when the report knows it; otherwise only the declaration line is
+    /// known to belong to the function.
+    pub end_line: Option<u32>,
+    /// Number of times the function was entered.
+    pub hits: u32,
+}
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BranchCounts {
+    pub covered: u32,
+    pub total: u32,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CoverageLookupError {
     pub path: String,
@@ -41,6 +92,13 @@ impl CoverageStore {
         for coverage in self.files.values_mut() {
             coverage.normalize_in_place();
         }
+        self.keys_by_name.clear();
+        for key in self.files.keys() {
+            self.keys_by_name
+                .entry(file_name(key).to_string())
+                .or_default()
+                .push(key.clone());
+        }
         self.normalized_ready = true;
     }
 
@@ -48,37 +106,140 @@ impl CoverageStore {
         &'a self,
         path: &str,
     ) -> Result<Option<Cow<'a, FileCoverage>>, CoverageLookupError> {
+        self.lookup(path).map(|(coverage, _)| coverage)
+    }
+
+    /// Like `file_coverage`, also describing how `path` was resolved when several coverage files
+    /// matched it.
+    pub fn lookup<'a>(&'a self, path: &str) -> Result<Lookup<'a>, CoverageLookupError> {
         if let Some(coverage) = self.coverage_for(path) {
-            return Ok(Some(coverage));
+            return Ok((Some(coverage), None));
         }
 
-        let mut matches = Vec::new();
-        for key in self.files.keys() {
-            if key.ends_with(path) || path.ends_with(key) {
-                matches.push(key);
-            }
+        let (matches, used) = self.resolve_matches(path);
+        if matches.len() > 1 && self.ambiguity == AmbiguityPolicy::Error {
+            return Err(CoverageLookupError {
+                path: path.to_string(),
+                matches,
+            });
         }
 
-        match matches.len() {
-            0 => Ok(None),
-            1 => Ok(self.coverage_for(matches[0]).map(Some).unwrap_or(None)),
+        let coverage = match used.len() {
+            0 => None,
+            1 => self.coverage_for(&used[0]),
             _ => {
                 let mut merged = FileCoverage::default();
-                for key in matches {
+                for key in &used {
                     if let Some(coverage) = self.coverage_for(key) {
-                        merged
-                            .measured_lines
-                            .extend(coverage.measured_lines.iter().copied());
-                        merged
-                            .covered_lines
-                            .extend(coverage.covered_lines.iter().copied());
+                        merged.merge(&coverage);
                     }
                 }
-                merged.dirty = true;
                 merged.normalize_in_place();
-                Ok(Some(Cow::Owned(merged)))
+                Some(Cow::Owned(merged))
+            }
+        };
+        let ambiguity = (matches.len() > 1).then_some(Ambiguity { matches, used });
+        Ok((coverage, ambiguity))
+    }
+
+    /// Returns every key matching `path`, sorted, and the keys the policy picks from them.
+    fn resolve_matches(&self, path: &str) -> (Vec<String>, Vec<String>) {
+        let mut matches: Vec<String> = if self.normalized_ready {
+            self.keys_by_name
+                .get(file_name(path))
+                .into_iter()
+                .flatten()
+                .filter(|key| same_trailing_components(key, path))
+                .cloned()
+                .collect()
+        } else {
+            self.files
+                .keys()
+                .filter(|key| same_trailing_components(key, path))
+                .cloned()
+                .collect()
+        };
+        matches.sort();
+
+        let used = if self.ambiguity == AmbiguityPolicy::PreferClosest {
+            let closest = matches
+                .iter()
+                .map(|key| shared_trailing_components(key, path))
+                .max()
+                .unwrap_or(0);
+            matches
+                .iter()
+                .filter(|key| shared_trailing_components(key, path) == closest)
+                .cloned()
+                .collect()
+        } else {
+            matches.clone()
+        };
+        (matches, used)
+    }
+
+    /// Measured and covered lines of every file whose key `in_scope` accepts.
+    pub fn totals(&self, in_scope: impl Fn(&str) -> bool) -> (usize, usize) {
+        self.files
+            .keys()
+            .filter(|path| in_scope(path))
+            .filter_map(|path| self.coverage_for(path))
+            .fold((0, 0), |(measured, covered), coverage| {
+                (
+                    measured + coverage.measured_lines.len(),
+                    covered + coverage.covered_lines.len(),
+                )
+            })
+    }
+
+    /// Whether any report carried branch counts.
+    pub fn has_branches(&self) -> bool {
+        self.files
+            .values()
+            .any(|coverage| !coverage.branches.is_empty())
+    }
+
+    /// Stops counting lines hit fewer than `min_hits` times as covered.
+    pub fn require_min_hits(&mut self, min_hits: u32) {
+        for coverage in self.files.values_mut() {
+            let hits = &coverage.hits;
+            coverage
+                .covered_lines
+                .retain(|line| hits.get(line).is_some_and(|hits| *hits >= min_hits));
+        }
+    }
+
+    /// Adds the coverage of a separately loaded report file. Hit counts are summed, since separate
+    /// reports usually come from separate test runs, e.g. shards of one suite.
+    pub fn add_report(&mut self, report: CoverageStore) {
+        for (path, coverage) in report.files {
+            match self.files.entry(path) {
+                Entry::Occupied(mut entry) => entry.get_mut().add_run(&coverage),
+                Entry::Vacant(entry) => {
+                    entry.insert(coverage);
+                }
             }
         }
+        if let Some(generated) = report.generated_at {
+            self.on_generated(generated);
+        }
+        self.normalized_ready = false;
+    }
+
+    /// Renames files whose path `rename` rewrites, merging files that end up under one path.
+    pub fn rename_files(&mut self, rename: impl Fn(&str) -> Option<String>) {
+        let mut renamed: HashMap<String, FileCoverage> = HashMap::with_capacity(self.files.len());
+        for (path, coverage) in self.files.drain() {
+            let path = rename(&path).unwrap_or(path);
+            match renamed.entry(path) {
+                Entry::Occupied(mut entry) => entry.get_mut().merge(&coverage),
+                Entry::Vacant(entry) => {
+                    entry.insert(coverage);
+                }
+            }
+        }
+        self.files = renamed;
+        self.normalized_ready = false;
     }
 
     fn coverage_for<'a>(&'a self, key: &str) -> Option<Cow<'a, FileCoverage>> {
@@ -91,6 +252,30 @@ impl CoverageStore {
     }
 }
 
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+fn components(path: &str) -> impl DoubleEndedIterator<Item = &str> {
+    path.split('/')
+        .filter(|component| !component.is_empty() && *component != ".")
+}
+
+fn shared_trailing_components(a: &str, b: &str) -> usize {
+    components(a)
+        .rev()
+        .zip(components(b).rev())
+        .take_while(|(left, right)| left == right)
+        .count()
+}
+
+/// True when the shorter path is made of the last whole components of the longer one, so
+/// `src/foo.rs` matches `/repo/src/foo.rs` but `oo.rs` does not match `foo.rs`.
+pub(super) fn same_trailing_components(a: &str, b: &str) -> bool {
+    let shared = shared_trailing_components(a, b);
+    shared > 0 && shared == components(a).count().min(components(b).count())
+}
+
 impl CoverageSink for CoverageStore {
     fn on_file(&mut self, file_path: &str) {
         self.normalized_ready = false;
@@ -102,6 +287,25 @@ impl CoverageSink for CoverageStore {
         let entry = self.files.entry(file_path.to_string()).or_default();
         entry.record_line(line, hits);
     }
+
+    fn on_branches(&mut self, file_path: &str, line: u32, counts: BranchCounts) {
+        self.normalized_ready = false;
+        let entry = self.files.entry(file_path.to_string()).or_default();
+        entry.record_branches(line, counts);
+    }
+
+    fn on_function(&mut self, file_path: &str, function: FunctionCoverage) {
+        self.normalized_ready = false;
+        let entry = self.files.entry(file_path.to_string()).or_default();
+        entry.record_function(function);
+    }
+
+    fn on_generated(&mut self, unix_seconds: u64) {
+        self.generated_at = Some(
+            self.generated_at
+                .map_or(unix_seconds, |oldest| oldest.min(unix_seconds)),
+        );
+    }
 }
 
 impl FileCoverage {
@@ -110,19 +314,87 @@ impl FileCoverage {
         if hits > 0 {
             self.covered_lines.push(line);
         }
+        let entry = self.hits.entry(line).or_default();
+        *entry = (*entry).max(hits);
+        self.dirty = true;
+    }
+
+    pub fn hits(&self, line: u32) -> u32 {
+        self.hits.get(&line).copied().unwrap_or(0)
+    }
+
+    /// Adds another entry's data for the same file, keeping the highest hit count of each line.
+    pub fn merge(&mut self, other: &FileCoverage) {
+        self.combine(other, u32::max);
+    }
+
+    /// Adds another test run's data for the same file, summing the hit counts of each line.
+    pub fn add_run(&mut self, other: &FileCoverage) {
+        self.combine(other, u32::saturating_add);
+    }
+
+    fn combine(&mut self, other: &FileCoverage, hits_of: fn(u32, u32) -> u32) {
+        self.measured_lines
+            .extend(other.measured_lines.iter().copied());
+        self.covered_lines
+            .extend(other.covered_lines.iter().copied());
+        for (line, hits) in &other.hits {
+            let entry = self.hits.entry(*line).or_default();
+            *entry = hits_of(*entry, *hits);
+        }
+        for (line, counts) in &other.branches {
+            self.record_branches(*line, *counts);
+        }
+        for function in other.functions.values() {
+            self.record_function(function.clone());
+        }
         self.dirty = true;
     }
 
+    /// Keeps the best counts when several reports describe the same line.
+    pub fn record_branches(&mut self, line: u32, counts: BranchCounts) {
+        let entry = self.branches.entry(line).or_default();
+        entry.covered = entry.covered.max(counts.covered);
+        entry.total = entry.total.max(counts.total);
+    }
+
+    /// Merges a function seen in several reports, keeping the highest entry count.
+    pub fn record_function(&mut self, function: FunctionCoverage) {
+        match self.functions.get_mut(&function.start_line) {
+            Some(existing) => {
+                existing.hits = existing.hits.max(function.hits);
+                existing.end_line = existing.end_line.max(function.end_line);
+            }
+            None => {
+                self.functions.insert(function.start_line, function);
+            }
+        }
+    }
+
+    /// Functions whose span contains at least one of `lines`. A function without a known end
+    /// spans only its declaration line, so code after it is not attributed to it.
+    pub fn functions_touching(&self, lines: &[u32]) -> Vec<&FunctionCoverage> {
+        self.functions
+            .values()
+            .filter(|function| {
+                let end = function.end_line.unwrap_or(function.start_line);
+                lines
+                    .iter()
+                    .any(|line| (function.start_line..=end).contains(line))
+            })
+            .collect()
+    }
+
     pub fn is_measured(&self, line: u32) -> bool {
         if self.dirty {
-            return self.measured_lines.iter().any(|value| *value == line);
+            return self.measured_lines.contains(&line);
         }
         self.measured_lines.binary_search(&line).is_ok()
     }
 
     pub fn is_covered(&self, line: u32) -> bool {
         if self.dirty {
-            return self.covered_lines.iter().any(|value| *value == line);
+            return self.covered_lines.contains(&line);
         }
         self.covered_lines.binary_search(&line).is_ok()
     }
@@ -149,7 +421,7 @@ fn sort_and_dedup(values: &mut Vec<u32>) {
 
 #[cfg(test)]
 mod tests {
-    use super::{CoverageStore, FileCoverage};
+    use super::{AmbiguityPolicy, BranchCounts, CoverageStore, FileCoverage, FunctionCoverage};
     use crate::coverage::CoverageSink;
 
     #[test]
@@ -187,6 +459,154 @@ mod tests {
         assert_eq!(exact.covered_lines, vec![10]);
     }
 
+    #[test]
+    fn matches_whole_path_components_only() {
+        let mut store = CoverageStore::default();
+        store.on_line("src/foo.rs", 1, 1);
+        store.on_line("data/b.rs", 2, 1);
+        store.on_line("/ci/checkout/lib/c.rs", 3, 1);
+        store.prepare_lookup();
+
+        assert!(store.file_coverage("oo.rs").expect("lookup").is_none());
+        assert!(store.file_coverage("a/b.rs").expect("lookup").is_none());
+        assert!(store.file_coverage("foo.rs").expect("lookup").is_some());
+        assert!(store
+            .file_coverage("repo/src/foo.rs")
+            .expect("lookup")
+            .is_some());
+        assert!(store.file_coverage("lib/c.rs").expect("lookup").is_some());
+        assert!(store.file_coverage("ib/c.rs").expect("lookup").is_none());
+    }
+
+    #[test]
+    fn applies_ambiguity_policy() {
+        let mut store = CoverageStore::default();
+        store.on_line("api/utils.py", 3, 1);
+        store.on_line("web/api/utils.py", 3, 0);
+        store.on_line("tools/utils.py", 4, 1);
+        store.prepare_lookup();
+
+        let (_, ambiguity) = store.lookup("svc/web/api/utils.py").expect("lookup");
+        let ambiguity = ambiguity.expect("ambiguous");
+        assert_eq!(
+            ambiguity.matches,
+            vec!["api/utils.py".to_string(), "web/api/utils.py".to_string()]
+        );
+        assert_eq!(ambiguity.used, ambiguity.matches);
+
+        store.ambiguity = AmbiguityPolicy::PreferClosest;
+        let (closest, ambiguity) = store.lookup("svc/web/api/utils.py").expect("lookup");
+        assert!(!closest.expect("coverage").is_covered(3));
+        assert_eq!(
+            ambiguity.expect("ambiguous").used,
+            vec!["web/api/utils.py".to_string()]
+        );
+
+        store.ambiguity = AmbiguityPolicy::Error;
+        let err = store.file_coverage("utils.py").expect_err("ambiguous");
+        assert_eq!(err.matches.len(), 3);
+        assert!(store.file_coverage("tools/utils.py").is_ok());
+    }
+
+    #[test]
+    fn keeps_hit_counts_for_min_hits() {
+        let mut store = CoverageStore::default();
+        store.on_line("src/foo.rs", 1, 1);
+        store.on_line("src/foo.rs", 2, 5);
+        store.on_line("src/foo.rs", 1, 2);
+        store.require_min_hits(3);
+        store.prepare_lookup();
+
+        let coverage = store
+            .file_coverage("src/foo.rs")
+            .expect("lookup")
+            .expect("coverage");
+        assert_eq!(coverage.hits(1), 2);
+        assert_eq!(coverage.covered_lines, vec![2]);
+        assert!(coverage.is_measured(1));
+    }
+
+    #[test]
+    fn finds_files_first_seen_after_lookup_was_prepared() {
+        let mut store = CoverageStore::default();
+        store.prepare_lookup();
+        store.on_branches(
+            "src/a.rs",
+            3,
+            BranchCounts {
+                covered: 1,
+                total: 2,
+            },
+        );
+        store.on_function(
+            "src/b.rs",
+            FunctionCoverage {
+                name: "run".to_string(),
+                start_line: 1,
+                end_line: None,
+                hits: 0,
+            },
+        );
+
+        assert!(store.file_coverage("a.rs").expect("lookup").is_some());
+        assert!(store.file_coverage("b.rs").expect("lookup").is_some());
+    }
+
+    #[test]
+    fn sums_hit_counts_across_reports() {
+        let mut store = CoverageStore::default();
+        for _ in 0..3 {
+            let mut shard = CoverageStore::default();
+            shard.on_line("src/foo.rs", 1, 1);
+            shard.on_line("src/foo.rs", 1, 1);
+            store.add_report(shard);
+        }
+        store.require_min_hits(2);
+        store.prepare_lookup();
+
+        let coverage = store
+            .file_coverage("src/foo.rs")
+            .expect("lookup")
+            .expect("coverage");
+        assert_eq!(coverage.hits(1), 3);
+        assert!(coverage.is_covered(1));
+    }
+
+    #[test]
+    fn sums_project_totals() {
+        let mut store = CoverageStore::default();
+        store.on_line("src/a.rs", 1, 1);
+        store.on_line("src/a.rs", 1, 1);
+        store.on_line("src/a.rs", 2, 0);
+        store.on_line("tests/b.rs", 1, 1);
+        store.prepare_lookup();
+
+        assert_eq!(store.totals(|_| true), (3, 2));
+        assert_eq!(store.totals(|path| path.starts_with("src/")), (2, 1));
+    }
+
+    #[test]
+    fn renames_and_merges_files() {
+        let mut store = CoverageStore::default();
+        store.on_line("/app/src/foo.rs", 10, 0);
+        store.on_line("/app/src/foo.rs", 12, 1);
+        store.on_line("src/foo.rs", 10, 3);
+        store.on_line("/app/src/bar.rs", 1, 1);
+
+        store.rename_files(|path| path.strip_prefix("/app/").map(str::to_string));
+        store.prepare_lookup();
+
+        let mut keys: Vec<_> = store.files.keys().cloned().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["src/bar.rs", "src/foo.rs"]);
+        let merged = store
+            .file_coverage("src/foo.rs")
+            .expect("lookup")
+            .expect("coverage");
+        assert_eq!(merged.measured_lines, vec![10, 12]);
+        assert_eq!(merged.covered_lines, vec![10, 12]);
+    }
+
     #[test]
     fn normalize_sorts_and_dedups_lines() {
         let mut coverage = FileCoverage::default();
@@ -199,4 +619,51 @@ mod tests {
         assert_eq!(normalized.measured_lines, vec![2, 3, 4]);
         assert_eq!(normalized.covered_lines, vec![2, 4]);
     }
+
+    #[test]
+    fn merges_branch_counts_for_the_same_line() {
+        let mut store = CoverageStore::default();
+        let counts = |covered, total| BranchCounts { covered, total };
+        store.on_branches("src/foo.rs", 7, counts(1, 2));
+        store.on_branches("src/foo.rs", 7, counts(2, 2));
+        store.on_branches("lib/foo.rs", 9, counts(0, 4));
+
+        let merged = store
+            .file_coverage("foo.rs")
+            .expect("lookup")
+            .expect("merged coverage");
+        assert_eq!(merged.branches.get(&7), Some(&counts(2, 2)));
+        assert_eq!(merged.branches.get(&9), Some(&counts(0, 4)));
+    }
+
+    #[test]
+    fn finds_functions_touching_changed_lines() {
+        let mut coverage = FileCoverage::default();
+        let function = |name: &str, start_line, end_line, hits| FunctionCoverage {
+            name: name.to_string(),
+            start_line,
+            end_line,
+            hits,
+        };
+        coverage.record_function(function("a", 1, Some(3), 2));
+        coverage.record_function(function("b", 10, None, 0));
+        coverage.record_function(function("c", 20, None, 0));
+        coverage.record_function(function("b", 10, None, 1));
+
+        let names = |lines: &[u32]| -> Vec<(String, u32)> {
+            coverage
+                .functions_touching(lines)
+                .into_iter()
+                .map(|function| (function.name.clone(), function.hits))
+                .collect()
+        };
+        assert_eq!(names(&[5]), Vec::new());
+        assert_eq!(
+            names(&[2, 10]),
+            vec![("a".to_string(), 2), ("b".to_string(), 1)]
+        );
+        // Without end lines, the body and code after the last function belong to no function.
+        assert_eq!(names(&[11, 19]), Vec::new());
+        assert_eq!(names(&[21, 500]), Vec::new());
+    }
 }
@@ -0,0 +1,104 @@
+use super::store::{CoverageLookupError, CoverageStore};
+use super::{analyze_changed_coverage, AnalysisOptions};
+use crate::diff::types::ChangedFile;
+use crate::report::ThresholdGroup;
+use crate::util::glob::path_or_directory_matches;
+
+/// A `--threshold GLOB=PERCENT` rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThresholdRule {
+    pub pattern: String,
+    pub percent: f64,
+}
+
+/// Assigns each changed file to the most specific matching rule and measures every rule's group.
+/// Files matching no rule are only held to the global `--fail-under`.
+pub fn evaluate_thresholds(
+    changed_files: &[ChangedFile],
+    coverage: &CoverageStore,
+    rules: &[ThresholdRule],
+    options: AnalysisOptions,
+) -> Result<Vec<ThresholdGroup>, CoverageLookupError> {
+    let mut groups: Vec<Vec<ChangedFile>> = vec![Vec::new(); rules.len()];
+    for file in changed_files {
+        if let Some(index) = most_specific_rule(rules, &file.path) {
+            groups[index].push(file.clone());
+        }
+    }
+
+    rules
+        .iter()
+        .zip(groups)
+        .map(|(rule, files)| {
+            let report = analyze_changed_coverage(&files, coverage, options)?;
+            Ok(ThresholdGroup {
+                pattern: rule.pattern.clone(),
+                threshold: rule.percent,
+                total_changed: report.total_changed,
+                total_covered: report.total_covered,
+            })
+        })
+        .collect()
+}
+
+/// The matching rule with the most literal characters; a later rule wins a tie.
+fn most_specific_rule(rules: &[ThresholdRule], path: &str) -> Option<usize> {
+    rules
+        .iter()
+        .enumerate()
+        .filter(|(_, rule)| path_or_directory_matches(&rule.pattern, path))
+        .max_by_key(|(_, rule)| literal_length(&rule.pattern))
+        .map(|(index, _)| index)
+}
+
+fn literal_length(pattern: &str) -> usize {
+    pattern
+        .chars()
+        .filter(|ch| !matches!(ch, '*' | '?' | '[' | ']' | '!'))
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{evaluate_thresholds, AnalysisOptions, ThresholdRule};
+    use crate::coverage::store::CoverageStore;
+    use crate::coverage::CoverageSink;
+    use crate::diff::types::ChangedFile;
+
+    #[test]
+    fn measures_each_file_against_its_most_specific_rule() {
+        let mut store = CoverageStore::default();
+        store.on_line("src/core/parser.rs", 1, 1);
+        store.on_line("src/core/parser.rs", 2, 0);
+        store.on_line("src/cli/args.rs", 1, 1);
+        store.on_line("src/cli/args.rs", 2, 0);
+        store.on_line("build.rs", 1, 0);
+        store.prepare_lookup();
+
+        let changed = vec![
+            ChangedFile::new("src/core/parser.rs", vec![1, 2]),
+            ChangedFile::new("src/cli/args.rs", vec![1, 2]),
+            ChangedFile::new("build.rs", vec![1]),
+        ];
+        let rules = vec![
+            ThresholdRule {
+                pattern: "src/**".to_string(),
+                percent: 40.0,
+            },
+            ThresholdRule {
+                pattern: "src/core/**".to_string(),
+                percent: 90.0,
+            },
+        ];
+        let groups = evaluate_thresholds(&changed, &store, &rules, AnalysisOptions::default())
+            .expect("evaluate");
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].pattern, "src/**");
+        assert_eq!((groups[0].total_covered, groups[0].total_changed), (1, 2));
+        assert!(groups[0].passed());
+        assert_eq!(groups[1].pattern, "src/core/**");
+        assert_eq!((groups[1].total_covered, groups[1].total_changed), (1, 2));
+        assert!(!groups[1].passed());
+    }
+}
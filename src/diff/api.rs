@@ -0,0 +1,232 @@
+use std::io::{Cursor, Read};
+
+use serde::Deserialize;
+
+use super::git::{parse_hunk_header, parse_unified_diff};
+use super::types::ChangedFile;
+
+/// Changed files from a saved code host API payload, plus the paths whose patch was left out.
+#[derive(Debug, Default)]
+pub struct ApiDiff {
+    pub files: Vec<ChangedFile>,
+    /// Paths of removed files, so patch series can drop them.
+    pub deleted: Vec<String>,
+    /// Files the API reported as changed without (or with a cut-off) patch text.
+    pub truncated: Vec<String>,
+    /// GitLab sets `overflow` when the MR has more changes than the response lists.
+    pub overflow: bool,
+}
+
+/// Returns true when the sample starts like a JSON document rather than diff text.
+pub fn can_parse(sample: &[u8]) -> bool {
+    sample
+        .iter()
+        .find(|byte| !byte.is_ascii_whitespace())
+        .is_some_and(|byte| matches!(byte, b'[' | b'{'))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Payload {
+    MergeRequestChanges(GitLabChanges),
+    PullRequestFiles(Vec<GitHubFile>),
+    MergeRequestDiffs(Vec<GitLabDiff>),
+}
+
+/// An entry of GitHub's "list pull request files" response.
+#[derive(Debug, Deserialize)]
+struct GitHubFile {
+    filename: String,
+    #[serde(default)]
+    status: String,
+    previous_filename: Option<String>,
+    patch: Option<String>,
+    #[serde(default)]
+    changes: u64,
+}
+
+/// GitLab's `GET /merge_requests/:iid/changes` response.
+#[derive(Debug, Deserialize)]
+struct GitLabChanges {
+    changes: Vec<GitLabDiff>,
+    #[serde(default)]
+    overflow: bool,
+}
+
+/// A GitLab change entry, as listed in `changes` or by `GET /merge_requests/:iid/diffs`.
+#[derive(Debug, Deserialize)]
+struct GitLabDiff {
+    old_path: String,
+    new_path: String,
+    #[serde(default)]
+    diff: String,
+    #[serde(default)]
+    new_file: bool,
+    #[serde(default)]
+    deleted_file: bool,
+    #[serde(default)]
+    too_large: bool,
+    #[serde(default)]
+    collapsed: bool,
+}
+
+/// Parses a saved GitHub PR files or GitLab MR changes payload.
+pub fn parse_api_diff<R: Read>(reader: R) -> Result<ApiDiff, String> {
+    let payload: Payload = serde_json::from_reader(reader).map_err(|err| {
+        format!("not a GitHub pull request files or GitLab merge request changes payload: {err}")
+    })?;
+
+    let mut result = ApiDiff::default();
+    match payload {
+        Payload::PullRequestFiles(files) => {
+            for file in files {
+                if file.status == "removed" {
+                    result.deleted.push(file.filename);
+                    continue;
+                }
+                let old_path = match file.status.as_str() {
+                    "added" => None,
+                    _ => Some(file.previous_filename.as_deref().unwrap_or(&file.filename)),
+                };
+                let patch = file.patch.as_deref().unwrap_or_default();
+                if (patch.is_empty() && file.changes > 0) || is_cut_off(patch) {
+                    result.truncated.push(file.filename.clone());
+                }
+                push_patch(&mut result, old_path, &file.filename, patch)?;
+            }
+        }
+        Payload::MergeRequestChanges(GitLabChanges { changes, overflow }) => {
+            result.overflow = overflow;
+            push_gitlab_diffs(&mut result, changes)?;
+        }
+        Payload::MergeRequestDiffs(diffs) => push_gitlab_diffs(&mut result, diffs)?,
+    }
+    Ok(result)
+}
+
+fn push_gitlab_diffs(result: &mut ApiDiff, diffs: Vec<GitLabDiff>) -> Result<(), String> {
+    for diff in diffs {
+        if diff.deleted_file {
+            result.deleted.push(diff.old_path);
+            continue;
+        }
+        if diff.too_large || (diff.collapsed && diff.diff.is_empty()) || is_cut_off(&diff.diff) {
+            result.truncated.push(diff.new_path.clone());
+        }
+        let old_path = (!diff.new_file).then_some(diff.old_path.as_str());
+        push_patch(result, old_path, &diff.new_path, &diff.diff)?;
+    }
+    Ok(())
+}
+
+/// True when a hunk has fewer body lines than its header announces.
+fn is_cut_off(patch: &str) -> bool {
+    let mut old_remaining = 0u32;
+    let mut new_remaining = 0u32;
+    for line in patch.lines() {
+        if line.starts_with("@@") {
+            if old_remaining > 0 || new_remaining > 0 {
+                return true;
+            }
+            if let Some(hunk) = parse_hunk_header(line) {
+                old_remaining = hunk.old_count;
+                new_remaining = hunk.new_count;
+            }
+            continue;
+        }
+        match line.chars().next() {
+            Some('+') => new_remaining = new_remaining.saturating_sub(1),
+            Some('-') => old_remaining = old_remaining.saturating_sub(1),
+            Some('\\') => {}
+            _ => {
+                old_remaining = old_remaining.saturating_sub(1);
+                new_remaining = new_remaining.saturating_sub(1);
+            }
+        }
+    }
+    old_remaining > 0 || new_remaining > 0
+}
+
+/// Both APIs return bare hunks without file headers; add them and reuse the unified parser.
+fn push_patch(
+    result: &mut ApiDiff,
+    old_path: Option<&str>,
+    path: &str,
+    patch: &str,
+) -> Result<(), String> {
+    let old_header = old_path.map_or("/dev/null".to_string(), |old| format!("a/{old}"));
+    let text = format!("--- {old_header}\n+++ b/{path}\n{patch}\n");
+    let files = parse_unified_diff(Cursor::new(text))
+        .map_err(|err| format!("invalid patch for {path}: {err}"))?;
+    result.files.extend(files);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{can_parse, parse_api_diff};
+    use std::io::Cursor;
+
+    #[test]
+    fn parses_github_pull_request_files() {
+        let json = r#"[
+            {"filename": "src/lib.rs", "status": "modified", "changes": 2,
+             "patch": "@@ -1,2 +1,3 @@\n fn a() {}\n+fn b() {}\n+fn c() {}\n-fn d() {}"},
+            {"filename": "src/new_name.rs", "status": "renamed",
+             "previous_filename": "src/old_name.rs", "changes": 1,
+             "patch": "@@ -3 +3 @@\n-x\n+y"},
+            {"filename": "src/gone.rs", "status": "removed", "changes": 4,
+             "patch": "@@ -1 +0,0 @@\n-gone"},
+            {"filename": "src/huge.rs", "status": "modified", "changes": 9000}
+        ]"#;
+        let diff = parse_api_diff(Cursor::new(json)).expect("parse");
+
+        assert_eq!(diff.files.len(), 3);
+        assert_eq!(diff.files[0].path, "src/lib.rs");
+        assert_eq!(diff.files[0].changed_lines, vec![2, 3]);
+        assert_eq!(diff.files[1].path, "src/new_name.rs");
+        assert_eq!(diff.files[1].old_path.as_deref(), Some("src/old_name.rs"));
+        assert_eq!(diff.files[1].changed_lines, vec![3]);
+        assert_eq!(diff.files[2].path, "src/huge.rs");
+        assert!(diff.files[2].changed_lines.is_empty());
+        assert_eq!(diff.truncated, vec!["src/huge.rs"]);
+        assert_eq!(diff.deleted, vec!["src/gone.rs"]);
+
+        let cut = r#"[{"filename": "a.rs", "status": "modified", "changes": 3,
+            "patch": "@@ -1,3 +1,3 @@\n a\n-b\n+c"}]"#;
+        let diff = parse_api_diff(Cursor::new(cut)).expect("parse");
+        assert_eq!(diff.truncated, vec!["a.rs"]);
+        assert_eq!(diff.files[0].changed_lines, vec![2]);
+    }
+
+    #[test]
+    fn parses_gitlab_merge_request_changes() {
+        let json = r#"{"iid": 7, "overflow": true, "changes": [
+            {"old_path": "app/a.py", "new_path": "app/b.py", "renamed_file": true,
+             "new_file": false, "deleted_file": false, "diff": "@@ -1 +1,2 @@\n x\n+y\n"},
+            {"old_path": "app/new.py", "new_path": "app/new.py", "new_file": true,
+             "deleted_file": false, "diff": "@@ -0,0 +1 @@\n+z\n"},
+            {"old_path": "app/old.py", "new_path": "app/old.py", "new_file": false,
+             "deleted_file": true, "diff": "@@ -1 +0,0 @@\n-z\n"},
+            {"old_path": "big.sql", "new_path": "big.sql", "too_large": true, "diff": ""}
+        ]}"#;
+        let diff = parse_api_diff(Cursor::new(json)).expect("parse");
+
+        assert!(diff.overflow);
+        assert_eq!(diff.files.len(), 3);
+        assert_eq!(diff.files[0].path, "app/b.py");
+        assert_eq!(diff.files[0].old_path.as_deref(), Some("app/a.py"));
+        assert_eq!(diff.files[0].changed_lines, vec![2]);
+        assert_eq!(diff.files[1].changed_lines, vec![1]);
+        assert!(diff.files[1].old_path.is_none());
+        assert_eq!(diff.truncated, vec!["big.sql"]);
+        assert_eq!(diff.deleted, vec!["app/old.py"]);
+    }
+
+    #[test]
+    fn detects_json_payloads() {
+        assert!(can_parse(b"  [{\"filename\": \"x\"}]"));
+        assert!(can_parse(b"\n{\"changes\": []}"));
+        assert!(!can_parse(b"diff --git a/x b/x"));
+    }
+}
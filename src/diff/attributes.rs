@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+
+use super::types::ChangedFile;
+use crate::util::glob::path_matches;
+use crate::util::source::SourceTree;
+
+/// Why a changed file was left out of the analysis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    Ignored,
+    Generated,
+    Vendored,
+    GeneratedHeader,
+}
+
+impl SkipReason {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Ignored => "diff-coverage=ignore",
+            Self::Generated => "linguist-generated",
+            Self::Vendored => "linguist-vendored",
+            Self::GeneratedHeader => "generated file header",
+        }
+    }
+}
+
+/// Removes files that `.gitattributes` marks as generated, vendored or `diff-coverage=ignore`,
+/// and files whose first lines carry a generated-code marker. Files are read from `source`, where
+/// nested `.gitattributes` files override the ones above them.
+pub fn skip_generated_files(
+    files: &mut Vec<ChangedFile>,
+    source: &SourceTree,
+) -> Vec<(String, SkipReason)> {
+    let mut attributes = AttributeFiles::new(source);
+    let mut skipped = Vec::new();
+    files.retain(|file| {
+        let reason = attributes.skip_reason(&file.path).or_else(|| {
+            has_generated_header(source, &file.path).then_some(SkipReason::GeneratedHeader)
+        });
+        match reason {
+            Some(reason) => {
+                skipped.push((file.path.clone(), reason));
+                false
+            }
+            None => true,
+        }
+    });
+    skipped
+}
+
+/// What one `.gitattributes` line does to an attribute: `attr`, `-attr` or `!attr`.
+#[derive(Debug, Clone, Copy)]
+enum Setting {
+    Set(bool),
+    Unspecified,
+}
+
+/// Attributes a line mentions; `None` leaves the value from earlier lines in place.
+#[derive(Debug, Default, Clone, Copy)]
+struct FileAttributes {
+    generated: Option<Setting>,
+    vendored: Option<Setting>,
+    ignored: Option<Setting>,
+}
+
+/// Resolved attribute values; `None` is unspecified.
+#[derive(Debug, Default)]
+struct AttributeState {
+    generated: Option<bool>,
+    vendored: Option<bool>,
+    ignored: Option<bool>,
+}
+
+fn apply(value: &mut Option<bool>, setting: Option<Setting>) {
+    match setting {
+        Some(Setting::Set(set)) => *value = Some(set),
+        Some(Setting::Unspecified) => *value = None,
+        None => {}
+    }
+}
+
+#[derive(Debug)]
+struct AttributeLine {
+    pattern: String,
+    attributes: FileAttributes,
+}
+
+/// Lazily loaded `.gitattributes` files, keyed by the directory they live in.
+struct AttributeFiles<'a> {
+    source: &'a SourceTree,
+    loaded: HashMap<String, Vec<AttributeLine>>,
+}
+
+impl<'a> AttributeFiles<'a> {
+    fn new(source: &'a SourceTree) -> Self {
+        Self {
+            source,
+            loaded: HashMap::new(),
+        }
+    }
+
+    fn skip_reason(&mut self, path: &str) -> Option<SkipReason> {
+        let mut state = AttributeState::default();
+        let mut dir = String::new();
+        let components: Vec<&str> = path.split('/').collect();
+        for (depth, component) in components.iter().enumerate() {
+            let relative = components[depth..].join("/");
+            for line in self.lines_in(&dir) {
+                if path_matches(&line.pattern, &relative) {
+                    let attributes = line.attributes;
+                    apply(&mut state.generated, attributes.generated);
+                    apply(&mut state.vendored, attributes.vendored);
+                    apply(&mut state.ignored, attributes.ignored);
+                }
+            }
+            dir.push_str(component);
+            dir.push('/');
+        }
+
+        if state.ignored == Some(true) {
+            Some(SkipReason::Ignored)
+        } else if state.generated == Some(true) {
+            Some(SkipReason::Generated)
+        } else if state.vendored == Some(true) {
+            Some(SkipReason::Vendored)
+        } else {
+            None
+        }
+    }
+
+    fn lines_in(&mut self, dir: &str) -> &[AttributeLine] {
+        let source = self.source;
+        self.loaded.entry(dir.to_string()).or_insert_with(|| {
+            source
+                .read_to_string(&format!("{dir}.gitattributes"))
+                .map(|text| parse_attributes(&text))
+                .unwrap_or_default()
+        })
+    }
+}
+
+fn parse_attributes(text: &str) -> Vec<AttributeLine> {
+    let mut lines = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut parts = line.split_whitespace();
+        let Some(pattern) = parts.next() else {
+            continue;
+        };
+        // Directory patterns never match files in gitattributes.
+        if pattern.ends_with('/') {
+            continue;
+        }
+        let mut attributes = FileAttributes::default();
+        for attribute in parts {
+            let (name, value) = match attribute.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (attribute, None),
+            };
+            let (name, setting) = if let Some(name) = name.strip_prefix('-') {
+                (name, Setting::Set(false))
+            } else if let Some(name) = name.strip_prefix('!') {
+                (name, Setting::Unspecified)
+            } else {
+                (
+                    name,
+                    Setting::Set(value.is_none_or(|value| value != "false")),
+                )
+            };
+            match name {
+                "linguist-generated" => attributes.generated = Some(setting),
+                "linguist-vendored" => attributes.vendored = Some(setting),
+                "diff-coverage" => {
+                    attributes.ignored = Some(match setting {
+                        Setting::Set(set) => Setting::Set(set && value == Some("ignore")),
+                        Setting::Unspecified => Setting::Unspecified,
+                    })
+                }
+                _ => {}
+            }
+        }
+        lines.push(AttributeLine {
+            pattern: pattern.to_string(),
+            attributes,
+        });
+    }
+    lines
+}
+
+/// Number of leading lines searched for a generated-code marker.
+const HEADER_LINES: usize = 10;
+
+/// Detects marker comments such as `// Code generated by protoc-gen-go. DO NOT EDIT.` or
+/// `# @generated` near the top of the file.
+fn has_generated_header(source: &SourceTree, path: &str) -> bool {
+    let Some(mut buf) = source.read(path) else {
+        return false;
+    };
+    buf.truncate(4096);
+    String::from_utf8_lossy(&buf)
+        .lines()
+        .take(HEADER_LINES)
+        .map(str::trim_start)
+        .filter(|line| {
+            ["//", "#", "/*", "*", "--", "<!--", ";", "%"]
+                .iter()
+                .any(|prefix| line.starts_with(prefix))
+        })
+        .any(|line| line.contains("@generated") || line.contains("DO NOT EDIT"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{skip_generated_files, SkipReason};
+    use crate::diff::types::ChangedFile;
+    use crate::util::source::SourceTree;
+
+    fn temp_root() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        std::fs::create_dir_all(dir.path().join("web/api")).expect("create dirs");
+        dir
+    }
+
+    #[test]
+    fn skips_files_marked_in_gitattributes() {
+        let dir = temp_root();
+        let root = dir.path();
+        std::fs::write(
+            root.join(".gitattributes"),
+            "# comment\n*.pb.go linguist-generated\nvendor/** linguist-vendored\nweb/** diff-coverage=ignore\n",
+        )
+        .expect("write attributes");
+        std::fs::write(
+            root.join("web/api/.gitattributes"),
+            "keep.ts -diff-coverage\n",
+        )
+        .expect("write nested attributes");
+
+        let mut files = vec![
+            ChangedFile::new("proto/api.pb.go", vec![1]),
+            ChangedFile::new("vendor/lib/x.go", vec![1]),
+            ChangedFile::new("web/api/client.ts", vec![1]),
+            ChangedFile::new("web/api/keep.ts", vec![1]),
+            ChangedFile::new("src/main.go", vec![1]),
+        ];
+        let skipped = skip_generated_files(&mut files, &SourceTree::Directory(root.to_path_buf()));
+
+        let kept: Vec<_> = files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(kept, vec!["web/api/keep.ts", "src/main.go"]);
+        assert_eq!(
+            skipped,
+            vec![
+                ("proto/api.pb.go".to_string(), SkipReason::Generated),
+                ("vendor/lib/x.go".to_string(), SkipReason::Vendored),
+                ("web/api/client.ts".to_string(), SkipReason::Ignored),
+            ]
+        );
+    }
+
+    #[test]
+    fn unspecified_attributes_reset_earlier_ones() {
+        let dir = temp_root();
+        let root = dir.path();
+        std::fs::write(
+            root.join(".gitattributes"),
+            "*.pb.go linguist-generated\napi.pb.go !linguist-generated\n",
+        )
+        .expect("write attributes");
+
+        let mut files = vec![
+            ChangedFile::new("proto/api.pb.go", vec![1]),
+            ChangedFile::new("proto/types.pb.go", vec![1]),
+        ];
+        let skipped = skip_generated_files(&mut files, &SourceTree::Directory(root.to_path_buf()));
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "proto/api.pb.go");
+        assert_eq!(
+            skipped,
+            vec![("proto/types.pb.go".to_string(), SkipReason::Generated)]
+        );
+    }
+
+    #[test]
+    fn skips_files_with_generated_headers() {
+        let dir = temp_root();
+        let root = dir.path();
+        std::fs::write(
+            root.join("web/api/schema.ts"),
+            "/* eslint-disable */\n// Code generated by openapi. DO NOT EDIT.\nexport const x = 1;\n",
+        )
+        .expect("write generated");
+        std::fs::write(
+            root.join("web/api/util.ts"),
+            "const note = \"DO NOT EDIT\";\n",
+        )
+        .expect("write source");
+
+        let mut files = vec![
+            ChangedFile::new("web/api/schema.ts", vec![3]),
+            ChangedFile::new("web/api/util.ts", vec![1]),
+        ];
+        let skipped = skip_generated_files(&mut files, &SourceTree::Directory(root.to_path_buf()));
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(
+            skipped,
+            vec![("web/api/schema.ts".to_string(), SkipReason::GeneratedHeader)]
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+use std::io::{BufRead, Read};
+
+use super::dialect::{DiffDialect, NewPath};
+use super::git::{finish_hunk, register_file, DiffParseError, DiffParseResult};
+use super::types::{ChangedFile, DiffLine, Hunk, Patch};
+
+const HUNK_SEPARATOR: &str = "***************";
+
+/// Returns true when the first hunk marker in the sample is a context diff `***************`
+/// rather than a unified `@@`.
+pub fn can_parse<R: Read>(reader: R) -> std::io::Result<bool> {
+    let mut limited = reader.take(8192);
+    let mut buf = Vec::new();
+    limited.read_to_end(&mut buf)?;
+    let sample = String::from_utf8_lossy(&buf);
+
+    for line in sample.lines() {
+        if line.starts_with("@@ ") {
+            return Ok(false);
+        }
+        if line.starts_with(HUNK_SEPARATOR) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    None,
+    Old,
+    New,
+}
+
+/// Parses `diff -c` output into the same changed files `parse_unified_patch` produces, also
+/// listing the files it deletes so patch series can drop them.
+pub fn parse_context_patch<R: BufRead>(reader: R) -> DiffParseResult<Patch> {
+    let mut deleted = Vec::new();
+    let mut old_path: Option<String> = None;
+    let mut files: HashMap<String, ChangedFile> = HashMap::new();
+    let mut order: Vec<String> = Vec::new();
+    let mut current_path: Option<String> = None;
+    let mut current_hunk: Option<Hunk> = None;
+    let mut section = Section::None;
+    let mut old_line: u32 = 0;
+    let mut new_line: u32 = 0;
+
+    for line_result in reader.lines() {
+        let line = line_result?;
+
+        if let Some(heading) = line.strip_prefix(HUNK_SEPARATOR) {
+            finish_hunk(&mut files, current_path.as_deref(), current_hunk.take());
+            section = Section::None;
+            if current_path.is_some() {
+                let heading = heading.trim_start_matches('*').trim();
+                current_hunk = Some(Hunk {
+                    old_start: 0,
+                    old_count: 0,
+                    new_start: 0,
+                    new_count: 0,
+                    section: (!heading.is_empty()).then(|| heading.to_string()),
+                    added: Vec::new(),
+                    removed: Vec::new(),
+                });
+            }
+            continue;
+        }
+
+        if let Some(hunk) = current_hunk.as_mut() {
+            if let Some(range) = parse_section_range(&line, "*** ", " ****") {
+                let (start, count) = range.ok_or_else(|| invalid_header(&line))?;
+                hunk.old_start = start;
+                hunk.old_count = count;
+                old_line = start;
+                section = Section::Old;
+                continue;
+            }
+            if let Some(range) = parse_section_range(&line, "--- ", " ----") {
+                let (start, count) = range.ok_or_else(|| invalid_header(&line))?;
+                hunk.new_start = start;
+                hunk.new_count = count;
+                new_line = start;
+                section = Section::New;
+                continue;
+            }
+
+            if let Some((marker, content)) = split_marker(&line) {
+                match (section, marker) {
+                    (Section::Old, '-' | '!') => {
+                        hunk.removed.push(DiffLine {
+                            number: old_line,
+                            content: content.to_string(),
+                        });
+                        old_line = old_line.saturating_add(1);
+                    }
+                    (Section::Old, ' ') => old_line = old_line.saturating_add(1),
+                    (Section::New, '+' | '!') => {
+                        hunk.added.push(DiffLine {
+                            number: new_line,
+                            content: content.to_string(),
+                        });
+                        new_line = new_line.saturating_add(1);
+                    }
+                    (Section::New, ' ') => new_line = new_line.saturating_add(1),
+                    _ => {}
+                }
+                continue;
+            }
+        }
+
+        if parse_section_range(&line, "*** ", " ****").is_some()
+            || parse_section_range(&line, "--- ", " ----").is_some()
+        {
+            // Range headers of a hunk that belongs to a skipped (deleted) file.
+            continue;
+        }
+
+        if let Some(path) = line.strip_prefix("*** ") {
+            finish_hunk(&mut files, current_path.as_deref(), current_hunk.take());
+            section = Section::None;
+            current_path = None;
+            old_path = DiffDialect::Plain.parse_old_path(path);
+            continue;
+        }
+
+        if let Some(path) = line.strip_prefix("--- ") {
+            finish_hunk(&mut files, current_path.as_deref(), current_hunk.take());
+            section = Section::None;
+            current_path = match DiffDialect::Plain.parse_new_path(path) {
+                NewPath::Deleted => {
+                    deleted.extend(old_path.take());
+                    None
+                }
+                NewPath::File(path) => Some(register_file(&mut files, &mut order, path)),
+            };
+        }
+    }
+    finish_hunk(&mut files, current_path.as_deref(), current_hunk.take());
+
+    let mut changed_files = Vec::with_capacity(order.len());
+    for path in order {
+        if let Some(file) = files.remove(&path) {
+            changed_files.push(file);
+        }
+    }
+
+    Ok(Patch {
+        files: changed_files,
+        deleted,
+    })
+}
+
+/// Parses `*** 10,15 ****` / `--- 10,15 ----`. The outer `None` means the line is not a range
+/// header; the inner one means it is malformed.
+fn parse_section_range(line: &str, prefix: &str, suffix: &str) -> Option<Option<(u32, u32)>> {
+    let body = line.strip_prefix(prefix)?.trim_end().strip_suffix(suffix)?;
+    let (start, end) = match body.split_once(',') {
+        Some((start, end)) => (start.parse::<u32>().ok(), end.parse::<u32>().ok()),
+        None => {
+            let value = body.parse::<u32>().ok();
+            (value, value)
+        }
+    };
+    let (Some(start), Some(end)) = (start, end) else {
+        return Some(None);
+    };
+    // An empty side is written as `0` (or `N,N-1` by some tools).
+    if start == 0 || end < start {
+        return Some(Some((start, 0)));
+    }
+    Some(Some((start, end - start + 1)))
+}
+
+fn split_marker(line: &str) -> Option<(char, &str)> {
+    let mut chars = line.chars();
+    let marker = chars.next()?;
+    if !matches!(marker, ' ' | '-' | '+' | '!') {
+        return None;
+    }
+    let rest = chars.as_str();
+    Some((marker, rest.strip_prefix(' ').unwrap_or(rest)))
+}
+
+fn invalid_header(line: &str) -> DiffParseError {
+    DiffParseError::InvalidHunkHeader(line.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{can_parse, parse_context_patch};
+    use std::io::Cursor;
+
+    const CONTEXT_DIFF: &str = "\
+*** a/src/calc.c	2024-01-01 10:00:00.000000000 +0000
+--- b/src/calc.c	2024-01-02 10:00:00.000000000 +0000
+***************
+*** 1,5 ****
+  int add(int a, int b)
+  {
+!     return a - b;
+- /* old */
+  }
+--- 1,6 ----
+  int add(int a, int b)
+  {
+!     return a + b;
++ }
++ int sub(int a, int b) {
++     return a - b;
+  }
+*************** int main(void)
+*** 20 ****
+--- 21,22 ----
++     run();
++     stop();
+*** a/src/gone.c	2024-01-01 10:00:00.000000000 +0000
+--- /dev/null	1970-01-01 00:00:00.000000000 +0000
+***************
+*** 1 ****
+- int gone;
+--- 0 ----
+";
+
+    #[test]
+    fn parses_changed_lines_and_hunks() {
+        let results = parse_context_patch(Cursor::new(CONTEXT_DIFF))
+            .expect("parse diff")
+            .files;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].path, "src/calc.c");
+        assert_eq!(results[0].changed_lines, vec![3, 4, 5, 6, 21, 22]);
+
+        let first = &results[0].hunks[0];
+        assert_eq!((first.old_start, first.old_count), (1, 5));
+        assert_eq!((first.new_start, first.new_count), (1, 6));
+        let removed: Vec<_> = first
+            .removed
+            .iter()
+            .map(|line| (line.number, line.content.as_str()))
+            .collect();
+        assert_eq!(removed, vec![(3, "    return a - b;"), (4, "/* old */")]);
+
+        let second = &results[0].hunks[1];
+        assert_eq!(second.section.as_deref(), Some("int main(void)"));
+        assert_eq!((second.new_start, second.new_count), (21, 2));
+    }
+
+    #[test]
+    fn lists_deleted_files() {
+        let patch = parse_context_patch(Cursor::new(CONTEXT_DIFF)).expect("parse diff");
+        assert_eq!(patch.deleted, vec!["src/gone.c"]);
+    }
+
+    #[test]
+    fn detects_context_diff_before_unified_markers() {
+        assert!(can_parse(Cursor::new(CONTEXT_DIFF)).expect("detect"));
+        let unified = "--- a/x\n+++ b/x\n@@ -1 +1 @@\n-a\n+b\n";
+        assert!(!can_parse(Cursor::new(unified)).expect("detect"));
+    }
+}
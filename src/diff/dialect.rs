@@ -0,0 +1,173 @@
+/// Diff flavors whose file headers differ from plain `diff -u` output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiffDialect {
+    #[default]
+    Plain,
+    Git,
+    Mercurial,
+    Subversion,
+    Perforce,
+}
+
+/// Path named by a `+++` header once dialect-specific decorations are removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NewPath {
+    File(String),
+    Deleted,
+}
+
+impl DiffDialect {
+    /// Recognizes the line that opens a file section, e.g. `diff --git`, `Index:` or `====`.
+    pub fn detect(line: &str) -> Option<Self> {
+        if line.starts_with("diff --git ") {
+            Some(Self::Git)
+        } else if line.starts_with("diff -r ") {
+            Some(Self::Mercurial)
+        } else if line.starts_with("Index: ") {
+            Some(Self::Subversion)
+        } else if perforce_depot_path(line).is_some() {
+            Some(Self::Perforce)
+        } else {
+            None
+        }
+    }
+
+    /// Parses the text following `--- `; `None` for newly added files.
+    pub fn parse_old_path(self, raw: &str) -> Option<String> {
+        let (path, _) = split_annotation(raw);
+        if path == "/dev/null" {
+            return None;
+        }
+        match self {
+            Self::Subversion | Self::Perforce => None,
+            Self::Plain | Self::Git | Self::Mercurial => {
+                Some(path.strip_prefix("a/").unwrap_or(path).to_string())
+            }
+        }
+    }
+
+    /// Parses the text following `+++ `.
+    pub fn parse_new_path(self, raw: &str) -> NewPath {
+        let (path, annotation) = split_annotation(raw);
+        if path == "/dev/null" {
+            return NewPath::Deleted;
+        }
+        match self {
+            Self::Subversion => {
+                let (path, annotation) = match annotation {
+                    Some(annotation) => (path, Some(annotation)),
+                    None => split_revision_suffix(path),
+                };
+                if annotation == Some("(nonexistent)") {
+                    NewPath::Deleted
+                } else {
+                    NewPath::File(path.to_string())
+                }
+            }
+            Self::Perforce => NewPath::File(strip_revision(path).to_string()),
+            Self::Plain | Self::Git | Self::Mercurial => {
+                NewPath::File(path.strip_prefix("b/").unwrap_or(path).to_string())
+            }
+        }
+    }
+}
+
+/// Extracts the depot path from a Perforce `==== //depot/path#rev ... ====` marker.
+pub fn perforce_depot_path(line: &str) -> Option<&str> {
+    let body = line
+        .strip_prefix("==== ")?
+        .trim_end()
+        .strip_suffix("====")?;
+    let path = body.split_whitespace().next()?;
+    path.starts_with("//").then(|| strip_revision(path))
+}
+
+/// Returns the depot path of a Perforce `--- //depot/path` header, if it is one.
+pub fn perforce_old_path(raw: &str) -> Option<&str> {
+    let (path, _) = split_annotation(raw);
+    path.starts_with("//").then(|| strip_revision(path))
+}
+
+/// Splits `path\ttimestamp` or `path\t(revision 12)` into its parts.
+fn split_annotation(raw: &str) -> (&str, Option<&str>) {
+    match raw.split_once('\t') {
+        Some((path, rest)) => (path.trim_end(), Some(rest.trim())),
+        None => (raw.trim_end(), None),
+    }
+}
+
+/// Splits Subversion's space-separated `path (working copy)` form.
+fn split_revision_suffix(raw: &str) -> (&str, Option<&str>) {
+    match raw.rfind(" (") {
+        Some(open) if raw.ends_with(')') => (&raw[..open], Some(&raw[open + 1..])),
+        _ => (raw, None),
+    }
+}
+
+fn strip_revision(path: &str) -> &str {
+    path.split_once('#').map_or(path, |(path, _)| path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{perforce_depot_path, DiffDialect, NewPath};
+
+    #[test]
+    fn detects_file_section_markers() {
+        assert_eq!(
+            DiffDialect::detect("diff --git a/x b/x"),
+            Some(DiffDialect::Git)
+        );
+        assert_eq!(
+            DiffDialect::detect("diff -r 9117c6561b0b -r 273ce12ad8f1 src/x.py"),
+            Some(DiffDialect::Mercurial)
+        );
+        assert_eq!(
+            DiffDialect::detect("Index: trunk/src/x.c"),
+            Some(DiffDialect::Subversion)
+        );
+        assert_eq!(
+            DiffDialect::detect("==== //depot/main/x.c#3 (text) ===="),
+            Some(DiffDialect::Perforce)
+        );
+        assert_eq!(DiffDialect::detect("--- a/x"), None);
+    }
+
+    #[test]
+    fn strips_dialect_annotations_from_new_path() {
+        assert_eq!(
+            DiffDialect::Subversion.parse_new_path("trunk/src/x.c\t(working copy)"),
+            NewPath::File("trunk/src/x.c".to_string())
+        );
+        assert_eq!(
+            DiffDialect::Subversion.parse_new_path("trunk/src/x.c (revision 1234)"),
+            NewPath::File("trunk/src/x.c".to_string())
+        );
+        assert_eq!(
+            DiffDialect::Subversion.parse_new_path("trunk/src/x.c\t(nonexistent)"),
+            NewPath::Deleted
+        );
+        assert_eq!(
+            DiffDialect::Mercurial.parse_new_path("b/src/x.py\tTue Oct 18 10:05:11 2005 -0700"),
+            NewPath::File("src/x.py".to_string())
+        );
+        assert_eq!(
+            DiffDialect::Git.parse_new_path("/dev/null"),
+            NewPath::Deleted
+        );
+        assert_eq!(
+            DiffDialect::Git.parse_old_path("a/src/old.rs"),
+            Some("src/old.rs".to_string())
+        );
+        assert_eq!(DiffDialect::Git.parse_old_path("/dev/null"), None);
+    }
+
+    #[test]
+    fn reads_perforce_depot_paths() {
+        assert_eq!(
+            perforce_depot_path("==== //depot/main/x.c#3 - /home/me/ws/main/x.c ===="),
+            Some("//depot/main/x.c")
+        );
+        assert_eq!(perforce_depot_path("==== not a depot ===="), None);
+    }
+}
@@ -0,0 +1,85 @@
+use super::types::ChangedFile;
+use crate::util::glob::path_or_directory_matches;
+
+/// Keeps files that match an `include` pattern, when any are given, and no `exclude` pattern.
+/// Returns each dropped path with the reason it was left out.
+pub fn filter_paths(
+    files: &mut Vec<ChangedFile>,
+    include: &[String],
+    exclude: &[String],
+) -> Vec<(String, String)> {
+    let mut skipped = Vec::new();
+    files.retain(|file| match skip_reason(&file.path, include, exclude) {
+        Some(reason) => {
+            skipped.push((file.path.clone(), reason));
+            false
+        }
+        None => true,
+    });
+    skipped
+}
+
+/// Why `path` falls outside the `--include`/`--exclude` scope, if it does.
+pub fn skip_reason(path: &str, include: &[String], exclude: &[String]) -> Option<String> {
+    if !include.is_empty()
+        && !include
+            .iter()
+            .any(|pattern| path_or_directory_matches(pattern, path))
+    {
+        return Some("not matched by --include".to_string());
+    }
+    exclude
+        .iter()
+        .find(|pattern| path_or_directory_matches(pattern, path))
+        .map(|pattern| format!("--exclude {pattern}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::filter_paths;
+    use crate::diff::types::ChangedFile;
+
+    #[test]
+    fn applies_include_then_exclude_patterns() {
+        let mut files = vec![
+            ChangedFile::new("app/models.py", vec![1]),
+            ChangedFile::new("app/migrations/0002_add_field.py", vec![1]),
+            ChangedFile::new("app/tests/helpers.py", vec![1]),
+            ChangedFile::new("docs/conf.py", vec![1]),
+            ChangedFile::new("app/vendor/lib.py", vec![1]),
+        ];
+        let skipped = filter_paths(
+            &mut files,
+            &["app/**".to_string()],
+            &[
+                "**/migrations/**".to_string(),
+                "helpers.py".to_string(),
+                "vendor".to_string(),
+            ],
+        );
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "app/models.py");
+        assert_eq!(
+            skipped,
+            vec![
+                (
+                    "app/migrations/0002_add_field.py".to_string(),
+                    "--exclude **/migrations/**".to_string()
+                ),
+                (
+                    "app/tests/helpers.py".to_string(),
+                    "--exclude helpers.py".to_string()
+                ),
+                (
+                    "docs/conf.py".to_string(),
+                    "not matched by --include".to_string()
+                ),
+                (
+                    "app/vendor/lib.py".to_string(),
+                    "--exclude vendor".to_string()
+                ),
+            ]
+        );
+    }
+}
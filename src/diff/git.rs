@@ -2,7 +2,8 @@ use std::collections::HashMap;
 use std::fmt;
 use std::io::{self, BufRead};
 
-use super::types::ChangedFile;
+use super::dialect::{perforce_depot_path, perforce_old_path, DiffDialect, NewPath};
+use super::types::{ChangedFile, DiffLine, Hunk, Patch};
 
 #[derive(Debug)]
 pub enum DiffParseError {
@@ -39,102 +40,219 @@ impl From<io::Error> for DiffParseError {
 pub type DiffParseResult<T> = Result<T, DiffParseError>;
 
 pub fn parse_unified_diff<R: BufRead>(reader: R) -> DiffParseResult<Vec<ChangedFile>> {
-    let mut files: HashMap<String, Vec<u32>> = HashMap::new();
+    parse_unified_patch(reader).map(|patch| patch.files)
+}
+
+/// Parses a unified diff, also listing the files it deletes so patch series can drop them.
+pub fn parse_unified_patch<R: BufRead>(reader: R) -> DiffParseResult<Patch> {
+    let mut deleted = Vec::new();
+    let mut files: HashMap<String, ChangedFile> = HashMap::new();
     let mut order: Vec<String> = Vec::new();
     let mut current_path: Option<String> = None;
-    let mut in_hunk = false;
+    let mut current_hunk: Option<Hunk> = None;
+    let mut dialect = DiffDialect::default();
+    let mut depot_path: Option<String> = None;
+    let mut old_path: Option<String> = None;
+    let mut gitlink = false;
     let mut old_line: u32 = 0;
     let mut new_line: u32 = 0;
+    let mut old_remaining: u32 = 0;
+    let mut new_remaining: u32 = 0;
 
     for line_result in reader.lines() {
         let line = line_result?;
+        let in_hunk_body = current_hunk.is_some() && (old_remaining > 0 || new_remaining > 0);
 
-        if let Some(path) = line.strip_prefix("+++ ") {
-            if path == "/dev/null" {
-                current_path = None;
+        if !in_hunk_body {
+            if let Some(detected) = DiffDialect::detect(&line) {
+                finish_hunk(&mut files, current_path.as_deref(), current_hunk.take());
+                dialect = detected;
+                old_path = None;
+                gitlink = false;
+                depot_path = perforce_depot_path(&line).map(str::to_string);
+                current_path = depot_path
+                    .clone()
+                    .map(|path| register_file(&mut files, &mut order, path));
                 continue;
             }
 
-            let normalized = path.strip_prefix("b/").unwrap_or(path).to_string();
+            if is_gitlink_header(&line) {
+                // A submodule pointer; `--submodule=diff` prints its contents as separate files.
+                gitlink = true;
+                continue;
+            }
 
-            if !files.contains_key(&normalized) {
-                order.push(normalized.clone());
-                files.insert(normalized.clone(), Vec::new());
+            if let Some(path) = line.strip_prefix("--- ") {
+                if let Some(path) = perforce_old_path(path) {
+                    dialect = DiffDialect::Perforce;
+                    depot_path = Some(path.to_string());
+                } else {
+                    old_path = dialect.parse_old_path(path);
+                }
+                continue;
             }
-            current_path = Some(normalized);
-            in_hunk = false;
-            continue;
-        }
 
-        if line.starts_with("@@") {
-            let Some(path) = current_path.as_ref() else {
-                in_hunk = false;
+            if let Some(path) = line.strip_prefix("rename from ") {
+                old_path = Some(path.to_string());
                 continue;
-            };
+            }
 
-            let (old_start, _old_count, new_start, _new_count) = parse_hunk_header(&line)
-                .ok_or_else(|| DiffParseError::InvalidHunkHeader(line.clone()))?;
+            if let Some(path) = line.strip_prefix("rename to ") {
+                // Pure renames have no ---/+++ headers but still move the file.
+                finish_hunk(&mut files, current_path.as_deref(), current_hunk.take());
+                let path = register_file(&mut files, &mut order, path.to_string());
+                set_old_path(&mut files, &path, old_path.clone());
+                current_path = Some(path);
+                continue;
+            }
 
-            old_line = old_start;
-            new_line = new_start;
-            in_hunk = true;
-            if let Some(lines) = files.get_mut(path) {
-                lines.reserve(4);
+            if let Some(path) = line.strip_prefix("+++ ") {
+                finish_hunk(&mut files, current_path.as_deref(), current_hunk.take());
+                current_path = match dialect.parse_new_path(path) {
+                    NewPath::Deleted => {
+                        deleted.extend(depot_path.take().or_else(|| old_path.take()));
+                        None
+                    }
+                    NewPath::File(_) if gitlink => None,
+                    // Perforce names the local workspace file here; the depot path is what
+                    // `--strip-prefix` is meant to work with.
+                    NewPath::File(path) => Some(depot_path.take().unwrap_or(path)),
+                }
+                .map(|path| register_file(&mut files, &mut order, path));
+                if let Some(path) = current_path.as_deref() {
+                    set_old_path(&mut files, path, old_path.take());
+                }
+                continue;
             }
-            continue;
-        }
 
-        if !in_hunk {
-            continue;
-        }
+            if line.starts_with("@@") {
+                finish_hunk(&mut files, current_path.as_deref(), current_hunk.take());
+                if current_path.is_none() {
+                    continue;
+                }
 
-        let Some(path) = current_path.as_ref() else {
+                let header = parse_hunk_header(&line)
+                    .ok_or_else(|| DiffParseError::InvalidHunkHeader(line.clone()))?;
+
+                old_line = header.old_start;
+                new_line = header.new_start;
+                old_remaining = header.old_count;
+                new_remaining = header.new_count;
+                current_hunk = Some(header);
+                continue;
+            }
+
+            finish_hunk(&mut files, current_path.as_deref(), current_hunk.take());
             continue;
-        };
+        }
 
-        let Some(lines) = files.get_mut(path) else {
+        let Some(hunk) = current_hunk.as_mut() else {
             continue;
         };
 
-        if line.starts_with('+') && !line.starts_with("+++") {
-            lines.push(new_line);
+        if let Some(content) = line.strip_prefix('+') {
+            hunk.added.push(DiffLine {
+                number: new_line,
+                content: content.to_string(),
+            });
             new_line = new_line.saturating_add(1);
+            new_remaining = new_remaining.saturating_sub(1);
             continue;
         }
 
-        if line.starts_with('-') && !line.starts_with("---") {
+        if let Some(content) = line.strip_prefix('-') {
+            hunk.removed.push(DiffLine {
+                number: old_line,
+                content: content.to_string(),
+            });
             old_line = old_line.saturating_add(1);
+            old_remaining = old_remaining.saturating_sub(1);
             continue;
         }
 
-        if line.starts_with(' ') {
+        if line.starts_with(' ') || line.is_empty() {
             old_line = old_line.saturating_add(1);
             new_line = new_line.saturating_add(1);
+            old_remaining = old_remaining.saturating_sub(1);
+            new_remaining = new_remaining.saturating_sub(1);
         }
     }
+    finish_hunk(&mut files, current_path.as_deref(), current_hunk.take());
 
     let mut changed_files = Vec::with_capacity(order.len());
     for path in order {
-        if let Some(lines) = files.remove(&path) {
-            changed_files.push(ChangedFile {
-                path,
-                changed_lines: lines,
-            });
+        if let Some(file) = files.remove(&path) {
+            changed_files.push(file);
+        }
+    }
+
+    Ok(Patch {
+        files: changed_files,
+        deleted,
+    })
+}
+
+/// Mode `160000` marks a submodule commit pointer rather than a file.
+fn is_gitlink_header(line: &str) -> bool {
+    (line.starts_with("index ") || line.starts_with("new file mode ")) && line.ends_with(" 160000")
+}
+
+pub(super) fn register_file(
+    files: &mut HashMap<String, ChangedFile>,
+    order: &mut Vec<String>,
+    path: String,
+) -> String {
+    if !files.contains_key(&path) {
+        order.push(path.clone());
+        files.insert(path.clone(), ChangedFile::new(path.clone(), Vec::new()));
+    }
+    path
+}
+
+fn set_old_path(files: &mut HashMap<String, ChangedFile>, path: &str, old_path: Option<String>) {
+    if let (Some(file), Some(old_path)) = (files.get_mut(path), old_path) {
+        if old_path != path {
+            file.old_path = Some(old_path);
         }
     }
+}
 
-    Ok(changed_files)
+pub(super) fn finish_hunk(
+    files: &mut HashMap<String, ChangedFile>,
+    path: Option<&str>,
+    hunk: Option<Hunk>,
+) {
+    let (Some(path), Some(hunk)) = (path, hunk) else {
+        return;
+    };
+    let Some(file) = files.get_mut(path) else {
+        return;
+    };
+    file.changed_lines
+        .extend(hunk.added.iter().map(|line| line.number));
+    file.hunks.push(hunk);
 }
 
-fn parse_hunk_header(line: &str) -> Option<(u32, u32, u32, u32)> {
+pub(super) fn parse_hunk_header(line: &str) -> Option<Hunk> {
     let body = line.strip_prefix("@@ ")?;
-    let body = body.split(" @@").next()?;
-    let mut parts = body.split_whitespace();
+    let (ranges, section) = match body.split_once(" @@") {
+        Some((ranges, rest)) => (ranges, rest.trim()),
+        None => (body, ""),
+    };
+    let mut parts = ranges.split_whitespace();
     let old_part = parts.next()?;
     let new_part = parts.next()?;
     let (old_start, old_count) = parse_range(old_part, '-')?;
     let (new_start, new_count) = parse_range(new_part, '+')?;
-    Some((old_start, old_count, new_start, new_count))
+    Some(Hunk {
+        old_start,
+        old_count,
+        new_start,
+        new_count,
+        section: (!section.is_empty()).then(|| section.to_string()),
+        added: Vec::new(),
+        removed: Vec::new(),
+    })
 }
 
 fn parse_range(part: &str, prefix: char) -> Option<(u32, u32)> {
@@ -157,7 +275,7 @@ fn parse_range(part: &str, prefix: char) -> Option<(u32, u32)> {
 
 #[cfg(test)]
 mod tests {
-    use super::parse_unified_diff;
+    use super::{parse_unified_diff, parse_unified_patch};
     use std::io::Cursor;
 
     #[test]
@@ -194,6 +312,22 @@ deleted file mode 100644
         assert!(results.is_empty());
     }
 
+    #[test]
+    fn lists_deleted_files() {
+        let diff = "\
+diff --git a/src/old.rs b/src/old.rs
+deleted file mode 100644
+--- a/src/old.rs
++++ /dev/null
+@@ -1,2 +0,0 @@
+-fn gone() {}
+-fn gone2() {}
+";
+        let patch = parse_unified_patch(Cursor::new(diff)).expect("parse diff");
+        assert!(patch.files.is_empty());
+        assert_eq!(patch.deleted, vec!["src/old.rs"]);
+    }
+
     #[test]
     fn changed_line() {
         let diff = r#"
@@ -255,4 +389,187 @@ index b3d9bbc..a766f11 100644
         assert_eq!(results[1].path, "test.php");
         assert_eq!(results[1].changed_lines, vec![2]);
     }
+
+    #[test]
+    fn keeps_hunk_ranges_section_and_content() {
+        let diff = "\
+diff --git a/src/lib.rs b/src/lib.rs
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -3,4 +3,4 @@ pub fn compute(value: u32) -> u32 {
+     let doubled = value * 2;
+-    doubled + 1
++    doubled + 2
++    // done
+-    // old
+ }
+@@ -20 +20,0 @@
+-fn unused() {}
+";
+        let results = parse_unified_diff(Cursor::new(diff)).expect("parse diff");
+        assert_eq!(results.len(), 1);
+        let hunks = &results[0].hunks;
+        assert_eq!(hunks.len(), 2);
+
+        let first = &hunks[0];
+        assert_eq!((first.old_start, first.old_count), (3, 4));
+        assert_eq!((first.new_start, first.new_count), (3, 4));
+        assert_eq!(
+            first.section.as_deref(),
+            Some("pub fn compute(value: u32) -> u32 {")
+        );
+        let added: Vec<_> = first
+            .added
+            .iter()
+            .map(|line| (line.number, line.content.as_str()))
+            .collect();
+        assert_eq!(added, vec![(4, "    doubled + 2"), (5, "    // done")]);
+        let removed: Vec<_> = first
+            .removed
+            .iter()
+            .map(|line| (line.number, line.content.as_str()))
+            .collect();
+        assert_eq!(removed, vec![(4, "    doubled + 1"), (5, "    // old")]);
+
+        let second = &hunks[1];
+        assert!(second.section.is_none());
+        assert!(second.added.is_empty());
+        assert_eq!(second.removed[0].number, 20);
+        assert_eq!(results[0].changed_lines, vec![4, 5]);
+    }
+
+    #[test]
+    fn treats_header_like_content_inside_hunk_as_lines() {
+        let diff = "\
+diff --git a/query.sql b/query.sql
+--- a/query.sql
++++ b/query.sql
+@@ -1,2 +1,2 @@
+--- old comment
++++ new comment
+ SELECT 1;
+";
+        let results = parse_unified_diff(Cursor::new(diff)).expect("parse diff");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].path, "query.sql");
+        assert_eq!(results[0].changed_lines, vec![1]);
+        assert_eq!(results[0].hunks[0].added[0].content, "++ new comment");
+        assert_eq!(results[0].hunks[0].removed[0].content, "-- old comment");
+    }
+
+    #[test]
+    fn parses_subversion_headers() {
+        let diff = "\
+Index: trunk/src/main.c
+===================================================================
+--- trunk/src/main.c	(revision 1234)
++++ trunk/src/main.c	(working copy)
+@@ -1,2 +1,3 @@
+ int main(void) {
++    init();
+     return 0;
+Index: trunk/src/old.c
+===================================================================
+--- trunk/src/old.c	(revision 1234)
++++ trunk/src/old.c	(nonexistent)
+@@ -1 +0,0 @@
+-int gone;
+";
+        let results = parse_unified_diff(Cursor::new(diff)).expect("parse diff");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].path, "trunk/src/main.c");
+        assert_eq!(results[0].changed_lines, vec![2]);
+    }
+
+    #[test]
+    fn parses_mercurial_headers() {
+        let diff = "\
+diff -r 9117c6561b0b -r 273ce12ad8f1 src/app.py
+--- a/src/app.py	Tue Oct 18 10:01:02 2005 -0700
++++ b/src/app.py	Tue Oct 18 10:05:11 2005 -0700
+@@ -1 +1,2 @@
+ import os
++import sys
+";
+        let results = parse_unified_diff(Cursor::new(diff)).expect("parse diff");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].path, "src/app.py");
+        assert_eq!(results[0].changed_lines, vec![2]);
+    }
+
+    #[test]
+    fn parses_perforce_describe_and_diff_headers() {
+        let diff = "\
+==== //depot/main/src/a.c#3 (text) ====
+
+@@ -1 +1,2 @@
+ int a;
++int b;
+--- //depot/main/src/b.c#7	2024/01/01 12:00:00
++++ /home/me/ws/main/src/b.c	2024/01/02 12:00:00
+@@ -4 +4 @@
+-int c;
++int d;
+";
+        let results = parse_unified_diff(Cursor::new(diff)).expect("parse diff");
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].path, "//depot/main/src/a.c");
+        assert_eq!(results[0].changed_lines, vec![2]);
+        assert_eq!(results[1].path, "//depot/main/src/b.c");
+        assert_eq!(results[1].changed_lines, vec![4]);
+    }
+
+    #[test]
+    fn records_renamed_paths() {
+        let diff = "\
+diff --git a/src/old.rs b/src/new.rs
+similarity index 90%
+rename from src/old.rs
+rename to src/new.rs
+--- a/src/old.rs
++++ b/src/new.rs
+@@ -1 +1,2 @@
+ fn a() {}
++fn b() {}
+diff --git a/src/same.rs b/src/same.rs
+--- a/src/same.rs
++++ b/src/same.rs
+@@ -1 +1,2 @@
+ fn c() {}
++fn d() {}
+";
+        let results = parse_unified_diff(Cursor::new(diff)).expect("parse diff");
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].path, "src/new.rs");
+        assert_eq!(results[0].old_path.as_deref(), Some("src/old.rs"));
+        assert_eq!(results[0].changed_lines, vec![2]);
+        assert!(results[1].old_path.is_none());
+    }
+
+    #[test]
+    fn skips_submodule_pointers_and_reads_submodule_diffs() {
+        let diff = "\
+Submodule libs/core 3245369..61ae02b:
+diff --git a/libs/core/x.c b/libs/core/x.c
+index 422c2b7..7be73ce 100644
+--- a/libs/core/x.c
++++ b/libs/core/x.c
+@@ -1,2 +1,3 @@
+ a
+-b
++B
++c
+diff --git a/libs/other b/libs/other
+index 3245369..61ae02b 160000
+--- a/libs/other
++++ b/libs/other
+@@ -1 +1 @@
+-Subproject commit 32453698f444d5305ad6330db0f6afc32489ea88
++Subproject commit 61ae02bca3fc0464c1c541ab345d85173d33e1e7
+";
+        let results = parse_unified_diff(Cursor::new(diff)).expect("parse diff");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].path, "libs/core/x.c");
+        assert_eq!(results[0].changed_lines, vec![2, 3]);
+    }
 }
@@ -0,0 +1,55 @@
+use std::collections::{HashMap, HashSet};
+
+use super::types::ChangedFile;
+
+/// Keeps the changed lines of `changed` that `since` also touches, e.g. the lines of a pull
+/// request that changed after the revision a reviewer already looked at. Lines `since` brings in
+/// from a rebase onto a newer base are dropped because they are not part of `changed`.
+pub fn restrict_to(changed: &[ChangedFile], since: &[ChangedFile]) -> Vec<ChangedFile> {
+    let since_lines: HashMap<&str, HashSet<u32>> = since
+        .iter()
+        .map(|file| {
+            (
+                file.path.as_str(),
+                file.changed_lines.iter().copied().collect(),
+            )
+        })
+        .collect();
+
+    changed
+        .iter()
+        .filter_map(|file| {
+            let lines = since_lines.get(file.path.as_str())?;
+            let mut restricted = file.clone();
+            restricted.changed_lines.retain(|line| lines.contains(line));
+            restricted.hunks.retain_mut(|hunk| {
+                hunk.added.retain(|line| lines.contains(&line.number));
+                !hunk.added.is_empty()
+            });
+            (!restricted.changed_lines.is_empty()).then_some(restricted)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::restrict_to;
+    use crate::diff::types::ChangedFile;
+
+    #[test]
+    fn keeps_only_lines_changed_in_both() {
+        let changed = vec![
+            ChangedFile::new("src/a.rs", vec![1, 2, 3, 10]),
+            ChangedFile::new("src/b.rs", vec![5]),
+        ];
+        let since = vec![
+            ChangedFile::new("src/a.rs", vec![3, 4, 10]),
+            ChangedFile::new("src/upstream.rs", vec![1]),
+        ];
+
+        let restricted = restrict_to(&changed, &since);
+        assert_eq!(restricted.len(), 1);
+        assert_eq!(restricted[0].path, "src/a.rs");
+        assert_eq!(restricted[0].changed_lines, vec![3, 10]);
+    }
+}
@@ -1,2 +1,14 @@
+pub mod api;
+pub mod attributes;
+pub mod context;
+pub mod dialect;
+pub mod filter;
 pub mod git;
+pub mod interdiff;
+pub mod moved;
+pub mod pragmas;
+pub mod selection;
+pub mod series;
+pub mod tree;
 pub mod types;
+pub mod whitespace;
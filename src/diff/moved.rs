@@ -0,0 +1,222 @@
+use std::collections::{HashMap, HashSet};
+
+use super::types::{ChangedFile, DiffLine};
+
+/// Detects added blocks whose content matches a removed block anywhere in the diff, similar to
+/// `git diff --color-moved`. Moved lines are taken out of `files` and returned as their own
+/// changed files so callers can drop them or measure them separately. Leading and trailing
+/// whitespace is ignored, so re-indented moves still count. A block must contain at least
+/// `min_lines` non-blank lines. Each removed line can be matched once, so duplicating code that
+/// was deleted once only counts one copy as moved.
+pub fn split_moved_lines(files: &mut [ChangedFile], min_lines: usize) -> Vec<ChangedFile> {
+    let min_lines = min_lines.max(1);
+    let removed_blocks = collect_blocks(
+        files
+            .iter()
+            .flat_map(|file| file.hunks.iter())
+            .map(|hunk| hunk.removed.as_slice()),
+    );
+    let mut index: HashMap<&str, Vec<(usize, usize)>> = HashMap::new();
+    for (block_idx, block) in removed_blocks.iter().enumerate() {
+        for (offset, content) in block.iter().enumerate() {
+            if !content.is_empty() {
+                index.entry(content).or_default().push((block_idx, offset));
+            }
+        }
+    }
+
+    let mut used: Vec<Vec<bool>> = removed_blocks
+        .iter()
+        .map(|block| vec![false; block.len()])
+        .collect();
+    let moved_per_file: Vec<HashSet<u32>> = files
+        .iter()
+        .map(|file| {
+            let mut moved = HashSet::new();
+            for hunk in &file.hunks {
+                for run in split_runs(&hunk.added) {
+                    mark_moved_run(
+                        run,
+                        &removed_blocks,
+                        &index,
+                        min_lines,
+                        &mut used,
+                        &mut moved,
+                    );
+                }
+            }
+            moved
+        })
+        .collect();
+
+    let mut moved_files = Vec::new();
+    for (file, moved) in files.iter_mut().zip(moved_per_file) {
+        if moved.is_empty() {
+            continue;
+        }
+
+        let mut moved_file = ChangedFile::new(file.path.clone(), Vec::new());
+        file.changed_lines.retain(|line| {
+            let is_moved = moved.contains(line);
+            if is_moved {
+                moved_file.changed_lines.push(*line);
+            }
+            !is_moved
+        });
+        for hunk in &mut file.hunks {
+            let mut moved_hunk = hunk.clone();
+            moved_hunk.added.retain(|line| moved.contains(&line.number));
+            hunk.added.retain(|line| !moved.contains(&line.number));
+            if !moved_hunk.added.is_empty() {
+                moved_file.hunks.push(moved_hunk);
+            }
+        }
+        moved_files.push(moved_file);
+    }
+
+    moved_files
+}
+
+fn collect_blocks<'a>(groups: impl Iterator<Item = &'a [DiffLine]>) -> Vec<Vec<&'a str>> {
+    groups
+        .flat_map(split_runs)
+        .map(|run| run.iter().map(|line| line.content.trim()).collect())
+        .collect()
+}
+
+/// Splits lines into runs of consecutive line numbers.
+fn split_runs(lines: &[DiffLine]) -> Vec<&[DiffLine]> {
+    let mut runs = Vec::new();
+    let mut start = 0;
+    for idx in 1..=lines.len() {
+        if idx == lines.len() || lines[idx].number != lines[idx - 1].number + 1 {
+            if start < idx {
+                runs.push(&lines[start..idx]);
+            }
+            start = idx;
+        }
+    }
+    runs
+}
+
+fn mark_moved_run(
+    run: &[DiffLine],
+    removed_blocks: &[Vec<&str>],
+    index: &HashMap<&str, Vec<(usize, usize)>>,
+    min_lines: usize,
+    used: &mut [Vec<bool>],
+    moved: &mut HashSet<u32>,
+) {
+    let added: Vec<&str> = run.iter().map(|line| line.content.trim()).collect();
+    let mut pos = 0;
+    while pos < added.len() {
+        let mut best = 0usize;
+        let mut best_source = (0usize, 0usize);
+        for &(block_idx, offset) in index.get(added[pos]).map(Vec::as_slice).unwrap_or(&[]) {
+            let block = &removed_blocks[block_idx][offset..];
+            let len = added[pos..]
+                .iter()
+                .zip(block)
+                .zip(&used[block_idx][offset..])
+                .take_while(|((added, removed), used)| added == removed && !**used)
+                .count();
+            if len > best {
+                best = len;
+                best_source = (block_idx, offset);
+            }
+        }
+
+        let significant = added[pos..pos + best]
+            .iter()
+            .filter(|content| !content.is_empty())
+            .count();
+        if best > 0 && significant >= min_lines {
+            let (block_idx, offset) = best_source;
+            used[block_idx][offset..offset + best].fill(true);
+            moved.extend(run[pos..pos + best].iter().map(|line| line.number));
+            pos += best;
+        } else {
+            pos += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split_moved_lines;
+    use crate::diff::git::parse_unified_diff;
+    use std::io::Cursor;
+
+    const MOVED_DIFF: &str = "\
+diff --git a/src/a.rs b/src/a.rs
+--- a/src/a.rs
++++ b/src/a.rs
+@@ -1,5 +1,1 @@
+ use std::fmt;
+-fn helper() -> u32 {
+-    let value = 1;
+-    value + 1
+-}
+diff --git a/src/b.rs b/src/b.rs
+--- a/src/b.rs
++++ b/src/b.rs
+@@ -1,1 +1,7 @@
+ use std::io;
++mod inner {
++    fn helper() -> u32 {
++        let value = 1;
++        value + 1
++    }
++}
+";
+
+    #[test]
+    fn splits_blocks_moved_between_files() {
+        let mut files = parse_unified_diff(Cursor::new(MOVED_DIFF)).expect("parse diff");
+        let moved = split_moved_lines(&mut files, 3);
+
+        assert_eq!(moved.len(), 1);
+        assert_eq!(moved[0].path, "src/b.rs");
+        assert_eq!(moved[0].changed_lines, vec![3, 4, 5, 6]);
+        assert_eq!(moved[0].hunks[0].added.len(), 4);
+
+        assert_eq!(files[1].changed_lines, vec![2, 7]);
+        let remaining: Vec<u32> = files[1].hunks[0]
+            .added
+            .iter()
+            .map(|line| line.number)
+            .collect();
+        assert_eq!(remaining, vec![2, 7]);
+    }
+
+    #[test]
+    fn matches_each_removed_block_once() {
+        let duplicated = MOVED_DIFF.to_string()
+            + "\
+diff --git a/src/c.rs b/src/c.rs
+--- a/src/c.rs
++++ b/src/c.rs
+@@ -1,1 +1,5 @@
+ use std::fs;
++fn helper() -> u32 {
++    let value = 1;
++    value + 1
++}
+";
+        let mut files = parse_unified_diff(Cursor::new(duplicated)).expect("parse diff");
+        let moved = split_moved_lines(&mut files, 3);
+
+        assert_eq!(moved.len(), 1);
+        assert_eq!(moved[0].path, "src/b.rs");
+        assert_eq!(files[2].changed_lines, vec![2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn ignores_blocks_shorter_than_minimum() {
+        let mut files = parse_unified_diff(Cursor::new(MOVED_DIFF)).expect("parse diff");
+        let moved = split_moved_lines(&mut files, 5);
+
+        assert!(moved.is_empty());
+        assert_eq!(files[1].changed_lines, vec![2, 3, 4, 5, 6, 7]);
+    }
+}
@@ -0,0 +1,308 @@
+use std::collections::BTreeSet;
+
+use regex::Regex;
+
+use super::types::ChangedFile;
+use crate::util::source::SourceTree;
+
+/// Drops changed lines that the current source marks as excluded from coverage:
+/// `pragma: no cover`, `LCOV_EXCL_LINE`, `LCOV_EXCL_START`/`LCOV_EXCL_STOP`,
+/// `@codeCoverageIgnore` (with its `Start`/`End` forms), `diff-coverage: ignore-next-line`, and
+/// lines matching any of `markers`. Files are read from `source`; files that cannot be read are
+/// left alone. Hunks keep every added line, so moved-code detection still sees whole blocks.
+/// Returns how many changed lines were excluded.
+pub fn exclude_marked_lines(
+    files: &mut [ChangedFile],
+    source: &SourceTree,
+    markers: &[Regex],
+) -> usize {
+    let mut excluded_total = 0usize;
+
+    for file in files.iter_mut() {
+        let Some(text) = source.read_to_string(&file.path) else {
+            continue;
+        };
+        let excluded = marked_lines(&text, markers);
+        if excluded.is_empty() {
+            continue;
+        }
+        let before = file.changed_lines.len();
+        file.changed_lines.retain(|line| !excluded.contains(line));
+        excluded_total += before - file.changed_lines.len();
+    }
+
+    excluded_total
+}
+
+/// Line numbers, starting at 1, excluded by markers in `source`.
+fn marked_lines(source: &str, markers: &[Regex]) -> BTreeSet<u32> {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut excluded = BTreeSet::new();
+    let mut in_range = false;
+
+    for (index, line) in lines.iter().enumerate() {
+        let number = index as u32 + 1;
+        if line.contains("LCOV_EXCL_START") || line.contains("@codeCoverageIgnoreStart") {
+            in_range = true;
+        }
+        if in_range {
+            excluded.insert(number);
+            if line.contains("LCOV_EXCL_STOP") || line.contains("@codeCoverageIgnoreEnd") {
+                in_range = false;
+            }
+            continue;
+        }
+
+        if line.contains("LCOV_EXCL_LINE") || markers.iter().any(|marker| marker.is_match(line)) {
+            excluded.insert(number);
+        } else if is_no_cover_pragma(line) {
+            excluded.insert(number);
+            // Like coverage.py, a pragma on a line opening a block excludes the whole block.
+            let code = &line[..comment_start(line).unwrap_or(line.len())];
+            if code.trim_end().ends_with(':') {
+                excluded.extend(indented_block(&lines, index));
+            }
+        } else if has_ignore_annotation(line) {
+            excluded.extend(braced_block(&lines, index));
+        } else if line.contains("diff-coverage: ignore-next-line") {
+            excluded.insert(number + 1);
+        }
+    }
+
+    excluded
+}
+
+/// Matches `# pragma: no cover` and the spellings coverage.py accepts, e.g. `pragma nocover`, in
+/// the line's comment.
+fn is_no_cover_pragma(line: &str) -> bool {
+    let Some(comment) = comment_start(line) else {
+        return false;
+    };
+    let lower = line[comment..].to_ascii_lowercase();
+    let Some(start) = lower.find("pragma") else {
+        return false;
+    };
+    let rest = lower[start + "pragma".len()..].trim_start_matches([':', ' ', '\t']);
+    let rest = rest.strip_prefix("no").unwrap_or("");
+    rest.trim_start().starts_with("cover")
+}
+
+/// Byte offset of the first `#`, `//` or `/*` outside a string literal.
+fn comment_start(line: &str) -> Option<usize> {
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    let mut chars = line.char_indices().peekable();
+    while let Some((index, ch)) = chars.next() {
+        if let Some(open) = quote {
+            if escaped {
+                escaped = false;
+            } else if ch == '\\' {
+                escaped = true;
+            } else if ch == open {
+                quote = None;
+            }
+            continue;
+        }
+        match ch {
+            '"' | '\'' | '`' => quote = Some(ch),
+            '#' => return Some(index),
+            '/' if chars
+                .peek()
+                .is_some_and(|(_, next)| matches!(next, '/' | '*')) =>
+            {
+                return Some(index)
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// PHPUnit's `@codeCoverageIgnore`, but not its `Start`/`End` range forms.
+fn has_ignore_annotation(line: &str) -> bool {
+    line.match_indices("@codeCoverageIgnore")
+        .any(|(index, marker)| {
+            !line[index + marker.len()..]
+                .chars()
+                .next()
+                .is_some_and(|ch| ch.is_ascii_alphanumeric())
+        })
+}
+
+/// Lines below `start` indented deeper than it, up to the first line that is not.
+fn indented_block(lines: &[&str], start: usize) -> Vec<u32> {
+    let depth = indentation(lines[start]);
+    let mut block = Vec::new();
+    for (index, line) in lines.iter().enumerate().skip(start + 1) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if indentation(line) <= depth {
+            break;
+        }
+        block.push(index as u32 + 1);
+    }
+    // Blank lines between excluded lines belong to the block too.
+    match (block.first(), block.last()) {
+        (Some(first), Some(last)) => (*first..=*last).collect(),
+        _ => block,
+    }
+}
+
+/// True for a line of a `/* ... */` or `/** ... */` comment.
+fn in_block_comment(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    trimmed.starts_with("/*") || trimmed.starts_with('*')
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Lines from `start` through the end of the next `{ ... }` block, or through the statement's
+/// `;` when no block follows, as with an abstract method. When `start` is inside a docblock, the
+/// search begins after its closing `*/`, so doc text such as `{@inheritdoc}` is not code.
+fn braced_block(lines: &[&str], start: usize) -> Vec<u32> {
+    let mut depth = 0usize;
+    let mut opened = false;
+    let mut end = start;
+    let code_from = lines
+        .iter()
+        .enumerate()
+        .skip(start)
+        .find(|(_, line)| line.contains("*/"))
+        .filter(|_| in_block_comment(lines[start]))
+        .map_or(start + 1, |(index, _)| index + 1);
+    'lines: for (index, line) in lines.iter().enumerate().skip(code_from) {
+        end = index;
+        for ch in line.chars() {
+            match ch {
+                '{' => {
+                    depth += 1;
+                    opened = true;
+                }
+                '}' if opened => {
+                    depth = depth.saturating_sub(1);
+                    if depth == 0 {
+                        break 'lines;
+                    }
+                }
+                ';' if !opened => break 'lines,
+                _ => {}
+            }
+        }
+    }
+    (start as u32 + 1..=end as u32 + 1).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{exclude_marked_lines, marked_lines};
+    use crate::diff::types::ChangedFile;
+    use crate::util::source::SourceTree;
+    use regex::Regex;
+
+    #[test]
+    fn finds_line_and_range_markers() {
+        let source = "\
+int f(int x) {
+    if (x < 0) abort(); // LCOV_EXCL_LINE
+    // LCOV_EXCL_START
+    debug_dump(x);
+    // LCOV_EXCL_STOP
+    // diff-coverage: ignore-next-line
+    trace(x);
+    return x; // NOCOV
+}
+";
+        let markers = vec![Regex::new("NOCOV").expect("regex")];
+        let lines: Vec<u32> = marked_lines(source, &markers).into_iter().collect();
+        assert_eq!(lines, vec![2, 3, 4, 5, 7, 8]);
+    }
+
+    #[test]
+    fn excludes_python_blocks_and_php_annotations() {
+        let python = "\
+def run():
+    if debug:  # pragma: no cover
+        dump()
+
+        log()
+    return 1
+";
+        let lines: Vec<u32> = marked_lines(python, &[]).into_iter().collect();
+        assert_eq!(lines, vec![2, 3, 4, 5]);
+
+        let php = "\
+class A {
+    /**
+     * @codeCoverageIgnore
+     */
+    public function debug()
+    {
+        if ($x) { dump($x); }
+    }
+
+    public function run() {}
+}
+";
+        let lines: Vec<u32> = marked_lines(php, &[]).into_iter().collect();
+        assert_eq!(lines, vec![3, 4, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn looks_for_the_annotated_block_after_the_docblock() {
+        let php = "\
+class A {
+    /**
+     * @codeCoverageIgnore
+     * {@inheritdoc}
+     * Returns early; see the parent.
+     */
+    public function debug()
+    {
+        dump($x);
+    }
+
+    public function run() {}
+}
+";
+        let lines: Vec<u32> = marked_lines(php, &[]).into_iter().collect();
+        assert_eq!(lines, vec![3, 4, 5, 6, 7, 8, 9, 10]);
+    }
+
+    #[test]
+    fn ignores_pragmas_outside_comments() {
+        let python = "\
+print(\"# pragma: no cover\")
+label = 'pragma: no cover'
+done = True  # pragma: no cover
+";
+        let lines: Vec<u32> = marked_lines(python, &[]).into_iter().collect();
+        assert_eq!(lines, vec![3]);
+    }
+
+    #[test]
+    fn drops_marked_changed_lines() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        std::fs::write(
+            dir.path().join("app.py"),
+            "x = 1\nraise SystemExit  # pragma: no cover\ny = 2\n",
+        )
+        .expect("write source");
+
+        let mut files = vec![
+            ChangedFile::new("app.py", vec![1, 2, 3]),
+            ChangedFile::new("missing.py", vec![1]),
+        ];
+        let excluded = exclude_marked_lines(
+            &mut files,
+            &SourceTree::Directory(dir.path().to_path_buf()),
+            &[],
+        );
+
+        assert_eq!(excluded, 1);
+        assert_eq!(files[0].changed_lines, vec![1, 3]);
+        assert_eq!(files[1].changed_lines, vec![1]);
+    }
+}
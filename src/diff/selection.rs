@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::io::Read;
+
+use serde_json::Value;
+
+use super::types::ChangedFile;
+
+/// Most lines a selection may hold, so that a typo such as `1-4000000000` is a usage error
+/// instead of an attempt to allocate gigabytes.
+const MAX_SELECTED_LINES: u64 = 1_000_000;
+
+/// Collects hand-picked line ranges into changed files, keeping the first-seen path order.
+#[derive(Debug, Default)]
+pub struct LineSelection {
+    order: Vec<String>,
+    lines: HashMap<String, Vec<u32>>,
+    total: u64,
+}
+
+impl LineSelection {
+    /// Adds a spec such as `src/parser.rs:120-180,src/lib.rs:10`. A segment without a path
+    /// continues the previous file, so `src/a.rs:1-3,7` selects lines 1-3 and 7.
+    pub fn add_spec(&mut self, spec: &str) -> Result<(), String> {
+        let mut current: Option<String> = None;
+        for segment in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (path, range) = match segment.rsplit_once(':') {
+                Some((path, range)) if !path.is_empty() => (path.to_string(), range),
+                _ => {
+                    let path = current.clone().ok_or_else(|| {
+                        format!("line selection '{segment}' must start with PATH:")
+                    })?;
+                    (path, segment)
+                }
+            };
+            let (start, end) = parse_range(range)
+                .ok_or_else(|| format!("invalid line range '{range}' for {path}"))?;
+            self.add_range(&path, start, end)?;
+            current = Some(path);
+        }
+        Ok(())
+    }
+
+    /// Adds a JSON object mapping paths to ranges, e.g.
+    /// `{"src/lib.rs": [10, [120, 180], "200-210"]}`.
+    pub fn add_json<R: Read>(&mut self, reader: R) -> Result<(), String> {
+        let value: Value = serde_json::from_reader(reader).map_err(|err| err.to_string())?;
+        let Value::Object(entries) = value else {
+            return Err("line selection JSON must be an object of {path: [ranges]}".to_string());
+        };
+        for (path, ranges) in entries {
+            let Value::Array(ranges) = ranges else {
+                return Err(format!("line ranges for {path} must be an array"));
+            };
+            for range in ranges {
+                let (start, end) = json_range(&range)
+                    .ok_or_else(|| format!("invalid line range {range} for {path}"))?;
+                self.add_range(&path, start, end)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn into_changed_files(mut self) -> Vec<ChangedFile> {
+        self.order
+            .into_iter()
+            .filter_map(|path| {
+                let mut lines = self.lines.remove(&path)?;
+                lines.sort_unstable();
+                lines.dedup();
+                Some(ChangedFile::new(path, lines))
+            })
+            .collect()
+    }
+
+    fn add_range(&mut self, path: &str, start: u32, end: u32) -> Result<(), String> {
+        self.total += u64::from(end - start) + 1;
+        if self.total > MAX_SELECTED_LINES {
+            return Err(format!(
+                "line range {start}-{end} for {path} selects more than {MAX_SELECTED_LINES} lines in total"
+            ));
+        }
+        if !self.lines.contains_key(path) {
+            self.order.push(path.to_string());
+        }
+        self.lines
+            .entry(path.to_string())
+            .or_default()
+            .extend(start..=end);
+        Ok(())
+    }
+}
+
+fn parse_range(raw: &str) -> Option<(u32, u32)> {
+    let (start, end) = match raw.split_once('-') {
+        Some((start, end)) => (start.trim().parse().ok()?, end.trim().parse().ok()?),
+        None => {
+            let line = raw.trim().parse().ok()?;
+            (line, line)
+        }
+    };
+    (start > 0 && start <= end).then_some((start, end))
+}
+
+fn json_range(value: &Value) -> Option<(u32, u32)> {
+    match value {
+        Value::Number(number) => {
+            let line = u32::try_from(number.as_u64()?).ok()?;
+            (line > 0).then_some((line, line))
+        }
+        Value::String(raw) => parse_range(raw),
+        Value::Array(bounds) if bounds.len() == 2 => {
+            let start = u32::try_from(bounds[0].as_u64()?).ok()?;
+            let end = u32::try_from(bounds[1].as_u64()?).ok()?;
+            (start > 0 && start <= end).then_some((start, end))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LineSelection;
+    use std::io::Cursor;
+
+    #[test]
+    fn parses_command_line_spec() {
+        let mut selection = LineSelection::default();
+        selection
+            .add_spec("src/parser.rs:120-122,src/lib.rs:10,12")
+            .expect("spec");
+        selection.add_spec("src/parser.rs:121").expect("spec");
+
+        let files = selection.into_changed_files();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path, "src/parser.rs");
+        assert_eq!(files[0].changed_lines, vec![120, 121, 122]);
+        assert_eq!(files[1].path, "src/lib.rs");
+        assert_eq!(files[1].changed_lines, vec![10, 12]);
+        assert!(files[0].hunks.is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_specs() {
+        let mut selection = LineSelection::default();
+        assert!(selection.add_spec("10-12").is_err());
+        assert!(selection.add_spec("src/a.rs:12-10").is_err());
+        assert!(selection.add_spec("src/a.rs:x").is_err());
+        assert!(selection.add_spec("src/a.rs:1-4000000000").is_err());
+    }
+
+    #[test]
+    fn parses_json_selection() {
+        let json = r#"{"src/lib.rs": [10, [20, 22], "30-31"]}"#;
+        let mut selection = LineSelection::default();
+        selection.add_json(Cursor::new(json)).expect("json");
+
+        let files = selection.into_changed_files();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].changed_lines, vec![10, 20, 21, 22, 30, 31]);
+
+        let mut selection = LineSelection::default();
+        assert!(selection.add_json(Cursor::new("[1, 2]")).is_err());
+    }
+}
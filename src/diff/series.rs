@@ -0,0 +1,461 @@
+use std::collections::HashMap;
+use std::io::BufRead;
+
+use super::types::{ChangedFile, DiffLine, Hunk, Patch};
+
+/// Returns true when the first non-empty line is an mbox `From ` separator, as written by
+/// `git format-patch`.
+pub fn is_mbox(first_line: &str) -> bool {
+    first_line.starts_with("From ")
+}
+
+/// Splits an mbox into the raw text of each message, in order.
+pub fn split_mbox<R: BufRead>(reader: R) -> std::io::Result<Vec<String>> {
+    let mut patches = Vec::new();
+    let mut current = String::new();
+    for line in reader.lines() {
+        let line = line?;
+        if is_mbox(&line) && !current.is_empty() {
+            patches.push(std::mem::take(&mut current));
+        }
+        current.push_str(&line);
+        current.push('\n');
+    }
+    if !current.is_empty() {
+        patches.push(current);
+    }
+    Ok(patches)
+}
+
+/// Applies patches in order and returns the changed lines of the whole series, expressed against
+/// the state after the last patch. Lines added by earlier patches are carried through the hunks of
+/// later ones, and dropped when a later patch removes them or deletes their file. Files touched by
+/// several patches get zero-context hunks numbered from the state before the first patch.
+pub fn combine_patches(patches: Vec<Patch>) -> Vec<ChangedFile> {
+    let mut combined: Vec<Option<ChangedFile>> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+
+    for patch in patches {
+        for path in &patch.deleted {
+            if let Some(slot) = index.remove(path) {
+                combined[slot] = None;
+            }
+        }
+        for file in patch.files {
+            let existing = file
+                .old_path
+                .as_deref()
+                .and_then(|old_path| index.remove(old_path))
+                .or_else(|| index.remove(&file.path));
+
+            let slot = match existing {
+                Some(slot) => {
+                    let Some(previous) = combined[slot].as_mut() else {
+                        continue;
+                    };
+                    previous.changed_lines = previous
+                        .changed_lines
+                        .iter()
+                        .filter_map(|line| map_line(&file.hunks, *line))
+                        .chain(file.changed_lines)
+                        .collect();
+                    previous.changed_lines.sort_unstable();
+                    previous.changed_lines.dedup();
+                    previous.hunks = compose_hunks(&previous.hunks, &file.hunks);
+                    previous.path = file.path.clone();
+                    if previous.old_path.is_none() {
+                        previous.old_path = file.old_path.clone();
+                    }
+                    slot
+                }
+                None => {
+                    combined.push(Some(file));
+                    combined.len() - 1
+                }
+            };
+            if let Some(file) = &combined[slot] {
+                index.insert(file.path.clone(), slot);
+            }
+        }
+    }
+
+    combined.into_iter().flatten().collect()
+}
+
+/// A removed or added line with the section heading of the hunk it came from.
+type SectionLine = (DiffLine, Option<String>);
+
+/// Combines `first` (base to intermediate) with `second` (intermediate to tip) into hunks from the
+/// base to the tip. Lines added by `first` and removed by `second` drop out of both sides.
+fn compose_hunks(first: &[Hunk], second: &[Hunk]) -> Vec<Hunk> {
+    let reversed: Vec<Hunk> = first.iter().map(reverse_hunk).collect();
+    let mut removed: Vec<SectionLine> = Vec::new();
+    let mut added: Vec<SectionLine> = Vec::new();
+
+    for hunk in first {
+        removed.extend(
+            hunk.removed
+                .iter()
+                .map(|line| (line.clone(), hunk.section.clone())),
+        );
+        for line in &hunk.added {
+            if let Some(number) = map_line(second, line.number) {
+                let line = DiffLine {
+                    number,
+                    content: line.content.clone(),
+                };
+                added.push((line, hunk.section.clone()));
+            }
+        }
+    }
+    for hunk in second {
+        for line in &hunk.removed {
+            // Only intermediate lines that existed in the base have a base line to remove.
+            if let Some(number) = map_line(&reversed, line.number) {
+                let line = DiffLine {
+                    number,
+                    content: line.content.clone(),
+                };
+                removed.push((line, hunk.section.clone()));
+            }
+        }
+        added.extend(
+            hunk.added
+                .iter()
+                .map(|line| (line.clone(), hunk.section.clone())),
+        );
+    }
+    removed.sort_by_key(|(line, _)| line.number);
+    added.sort_by_key(|(line, _)| line.number);
+    build_hunks(removed, added)
+}
+
+/// The same hunk read from the new side to the old one.
+fn reverse_hunk(hunk: &Hunk) -> Hunk {
+    Hunk {
+        old_start: hunk.new_start,
+        old_count: hunk.new_count,
+        new_start: hunk.old_start,
+        new_count: hunk.old_count,
+        section: hunk.section.clone(),
+        added: hunk.removed.clone(),
+        removed: hunk.added.clone(),
+    }
+}
+
+/// Groups removed old lines and added new lines into zero-context hunks, written the way
+/// `git diff -U0` does: an empty side starts at the line before the change.
+fn build_hunks(removed: Vec<SectionLine>, added: Vec<SectionLine>) -> Vec<Hunk> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    let mut removed = removed.into_iter().peekable();
+    let mut added = added.into_iter().peekable();
+    // New line number minus old line number for the unchanged lines at the current position.
+    let mut delta: i64 = 0;
+
+    loop {
+        let take_removed = match (removed.peek(), added.peek()) {
+            (Some((old, _)), Some((new, _))) => {
+                i64::from(old.number) + delta <= i64::from(new.number)
+            }
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => break,
+        };
+        let next = if take_removed {
+            removed.next()
+        } else {
+            added.next()
+        };
+        let Some((line, section)) = next else {
+            break;
+        };
+        let number = i64::from(line.number);
+        let (old_pos, new_pos) = if take_removed {
+            (number, number + delta)
+        } else {
+            (number - delta, number)
+        };
+
+        let continues = hunks.last().is_some_and(|hunk| {
+            i64::from(hunk.old_start + hunk.old_count) == old_pos
+                && i64::from(hunk.new_start + hunk.new_count) == new_pos
+        });
+        if !continues {
+            hunks.push(Hunk {
+                old_start: u32::try_from(old_pos).unwrap_or(0),
+                old_count: 0,
+                new_start: u32::try_from(new_pos).unwrap_or(0),
+                new_count: 0,
+                section: None,
+                added: Vec::new(),
+                removed: Vec::new(),
+            });
+        }
+        let Some(hunk) = hunks.last_mut() else {
+            break;
+        };
+        if hunk.section.is_none() {
+            hunk.section = section;
+        }
+        if take_removed {
+            hunk.old_count += 1;
+            hunk.removed.push(line);
+            delta -= 1;
+        } else {
+            hunk.new_count += 1;
+            hunk.added.push(line);
+            delta += 1;
+        }
+    }
+
+    for hunk in &mut hunks {
+        if hunk.old_count == 0 {
+            hunk.old_start -= 1;
+        }
+        if hunk.new_count == 0 {
+            hunk.new_start -= 1;
+        }
+    }
+    hunks
+}
+
+/// Maps an old-side line number through a patch's hunks; `None` when the patch removes it.
+pub fn map_line(hunks: &[Hunk], line: u32) -> Option<u32> {
+    let mut delta: i64 = 0;
+    for hunk in hunks {
+        // A pure insertion (`-10,0`) goes after old line 10.
+        let first = if hunk.old_count == 0 {
+            hunk.old_start + 1
+        } else {
+            hunk.old_start
+        };
+        let end = first + hunk.old_count;
+        if line < first {
+            break;
+        }
+        if line < end {
+            if hunk.removed.iter().any(|removed| removed.number == line) {
+                return None;
+            }
+            let removed_before = hunk
+                .removed
+                .iter()
+                .filter(|removed| removed.number < line)
+                .count() as u32;
+            // Context lines fill the new-side slots that added lines leave free, in order.
+            let mut new_line = hunk.new_start + (line - hunk.old_start) - removed_before;
+            for added in &hunk.added {
+                if added.number <= new_line {
+                    new_line += 1;
+                }
+            }
+            return Some(new_line);
+        }
+        delta += i64::from(hunk.new_count) - i64::from(hunk.old_count);
+    }
+    u32::try_from(i64::from(line) + delta).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{combine_patches, map_line, split_mbox};
+    use crate::diff::git::parse_unified_patch;
+    use crate::diff::types::{DiffLine, Hunk, Patch};
+    use std::io::Cursor;
+
+    fn parse(diff: &str) -> Patch {
+        parse_unified_patch(Cursor::new(diff)).expect("parse diff")
+    }
+
+    type HunkSummary<'a> = ((u32, u32, u32, u32), Vec<&'a str>, Vec<&'a str>);
+
+    fn contents(lines: &[DiffLine]) -> Vec<&str> {
+        lines.iter().map(|line| line.content.as_str()).collect()
+    }
+
+    fn hunk_summary(hunks: &[Hunk]) -> Vec<HunkSummary<'_>> {
+        hunks
+            .iter()
+            .map(|hunk| {
+                (
+                    (
+                        hunk.old_start,
+                        hunk.old_count,
+                        hunk.new_start,
+                        hunk.new_count,
+                    ),
+                    contents(&hunk.removed),
+                    contents(&hunk.added),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn maps_lines_through_hunks() {
+        let files = parse(
+            "\
+--- a/f
++++ b/f
+@@ -2,4 +2,4 @@
+ two
+-three
++THREE
++three and a half
+ four
+-five
+@@ -10,0 +11,2 @@
++eleven
++twelve
+",
+        );
+        let hunks = &files.files[0].hunks;
+        assert_eq!(map_line(hunks, 1), Some(1));
+        assert_eq!(map_line(hunks, 2), Some(2));
+        assert_eq!(map_line(hunks, 3), None);
+        assert_eq!(map_line(hunks, 4), Some(5));
+        assert_eq!(map_line(hunks, 5), None);
+        assert_eq!(map_line(hunks, 6), Some(6));
+        assert_eq!(map_line(hunks, 10), Some(10));
+        assert_eq!(map_line(hunks, 11), Some(13));
+    }
+
+    #[test]
+    fn carries_earlier_lines_through_later_patches() {
+        let first = parse(
+            "\
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1 +1,3 @@
+ fn a() {}
++fn b() {}
++fn c() {}
+",
+        );
+        let second = parse(
+            "\
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,2 +1,2 @@
++fn header() {}
+ fn a() {}
+-fn b() {}
+@@ -5 +5,2 @@
+ fn tail() {}
++fn d() {}
+",
+        );
+
+        let combined = combine_patches(vec![first, second]);
+        assert_eq!(combined.len(), 1);
+        assert_eq!(combined[0].changed_lines, vec![1, 3, 6]);
+        assert_eq!(
+            hunk_summary(&combined[0].hunks),
+            vec![
+                ((0, 0, 1, 1), vec![], vec!["fn header() {}"]),
+                ((1, 0, 3, 1), vec![], vec!["fn c() {}"]),
+                ((3, 0, 6, 1), vec![], vec!["fn d() {}"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn numbers_combined_hunks_from_the_base() {
+        let first = parse(
+            "\
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -2 +2,2 @@
+-x
++X1
++X2
+",
+        );
+        let second = parse(
+            "\
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -4,2 +4,2 @@
+-y
+ tail
++z
+",
+        );
+
+        let combined = combine_patches(vec![first, second]);
+        assert_eq!(combined[0].changed_lines, vec![2, 3, 5]);
+        assert_eq!(
+            hunk_summary(&combined[0].hunks),
+            vec![
+                ((2, 2, 2, 2), vec!["x", "y"], vec!["X1", "X2"]),
+                ((4, 0, 5, 1), vec![], vec!["z"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn follows_renames_between_patches() {
+        let first = parse(
+            "\
+--- a/src/old.rs
++++ b/src/old.rs
+@@ -1 +1,2 @@
+ fn a() {}
++fn b() {}
+",
+        );
+        let second = parse(
+            "\
+diff --git a/src/old.rs b/src/new.rs
+rename from src/old.rs
+rename to src/new.rs
+",
+        );
+
+        let combined = combine_patches(vec![first, second]);
+        assert_eq!(combined.len(), 1);
+        assert_eq!(combined[0].path, "src/new.rs");
+        assert_eq!(combined[0].changed_lines, vec![2]);
+    }
+
+    #[test]
+    fn drops_files_deleted_by_later_patches() {
+        let first = parse(
+            "\
+--- a/src/gone.rs
++++ b/src/gone.rs
+@@ -1 +1,2 @@
+ fn a() {}
++fn b() {}
+",
+        );
+        let second = parse(
+            "\
+diff --git a/src/gone.rs b/src/gone.rs
+deleted file mode 100644
+--- a/src/gone.rs
++++ /dev/null
+@@ -1,2 +0,0 @@
+-fn a() {}
+-fn b() {}
+",
+        );
+
+        assert!(combine_patches(vec![first, second]).is_empty());
+    }
+
+    #[test]
+    fn splits_mbox_messages() {
+        let mbox = "\
+From 1111111111111111111111111111111111111111 Mon Sep 17 00:00:00 2001
+Subject: [PATCH 1/2] one
+
+---
+From 2222222222222222222222222222222222222222 Mon Sep 17 00:00:00 2001
+Subject: [PATCH 2/2] two
+";
+        let patches = split_mbox(Cursor::new(mbox)).expect("split");
+        assert_eq!(patches.len(), 2);
+        assert!(patches[1].contains("[PATCH 2/2]"));
+    }
+}
@@ -0,0 +1,337 @@
+use std::io;
+use std::path::Path;
+
+use super::types::{ChangedFile, DiffLine, Hunk};
+use crate::util::path::normalize_path;
+
+/// Context lines kept around each change, as in `diff -u`.
+const CONTEXT_LINES: usize = 3;
+
+/// Compares two source trees (or two files) and returns the same changed files a unified diff
+/// between them would. Files only present in the old tree are deleted and skipped; binary files
+/// are ignored.
+pub fn diff_trees(old_root: &Path, new_root: &Path) -> io::Result<Vec<ChangedFile>> {
+    if new_root.is_file() {
+        let path = normalize_path(&new_root.to_string_lossy());
+        let old_path = old_root.is_file().then_some(old_root);
+        return Ok(diff_file(&path, old_path, new_root)?.into_iter().collect());
+    }
+
+    let mut relative_paths = Vec::new();
+    list_files(new_root, Path::new(""), &mut relative_paths)?;
+    relative_paths.sort();
+
+    let mut changed = Vec::new();
+    for relative in relative_paths {
+        let old_path = old_root.join(&relative);
+        let old_path = old_path.is_file().then_some(old_path.as_path());
+        let path = normalize_path(&relative.to_string_lossy());
+        changed.extend(diff_file(&path, old_path, &new_root.join(&relative))?);
+    }
+    Ok(changed)
+}
+
+fn list_files(root: &Path, relative: &Path, files: &mut Vec<std::path::PathBuf>) -> io::Result<()> {
+    for entry in std::fs::read_dir(root.join(relative))? {
+        let entry = entry?;
+        let entry_relative = relative.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            list_files(root, &entry_relative, files)?;
+        } else if file_type.is_file() {
+            files.push(entry_relative);
+        }
+    }
+    Ok(())
+}
+
+fn diff_file(path: &str, old: Option<&Path>, new: &Path) -> io::Result<Option<ChangedFile>> {
+    let Some(new_text) = read_text(new)? else {
+        return Ok(None);
+    };
+    let old_text = match old {
+        Some(old) => match read_text(old)? {
+            Some(text) => text,
+            None => return Ok(None),
+        },
+        None => String::new(),
+    };
+    Ok(diff_text(path, &old_text, &new_text))
+}
+
+/// Reads a file as text, or `None` when it looks binary.
+fn read_text(path: &Path) -> io::Result<Option<String>> {
+    let bytes = std::fs::read(path)?;
+    if bytes.contains(&0) {
+        return Ok(None);
+    }
+    Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+/// Diffs two versions of one file; `None` when their lines are identical.
+pub fn diff_text(path: &str, old: &str, new: &str) -> Option<ChangedFile> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let edits = diff_lines(&old_lines, &new_lines);
+    if edits.iter().all(|edit| matches!(edit, Edit::Equal)) {
+        return None;
+    }
+
+    let mut file = ChangedFile::new(path, Vec::new());
+    for hunk in build_hunks(&edits, &old_lines, &new_lines) {
+        file.changed_lines
+            .extend(hunk.added.iter().map(|line| line.number));
+        file.hunks.push(hunk);
+    }
+    Some(file)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Myers' O(ND) shortest edit script, after trimming the common prefix and suffix.
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    let prefix = old
+        .iter()
+        .zip(new)
+        .take_while(|(old, new)| old == new)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+
+    let mut edits = vec![Edit::Equal; prefix];
+    edits.extend(shortest_edit(a, b));
+    edits.extend(std::iter::repeat_n(Edit::Equal, suffix));
+    edits
+}
+
+/// Edit distance beyond which the files are treated as rewritten; keeps the trace, which grows
+/// with the square of the distance, to a few dozen megabytes.
+const MAX_EDIT_DISTANCE: isize = 2_000;
+
+fn shortest_edit(a: &[&str], b: &[&str]) -> Vec<Edit> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = n + m;
+    if max == 0 {
+        return Vec::new();
+    }
+    let index = |k: isize| (k + max) as usize;
+    let mut v = vec![0isize; 2 * max as usize + 2];
+    // Before each round `d`, the diagonals `-d..=d` that the backtrack reads.
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    'search: for d in 0..=max {
+        if d > MAX_EDIT_DISTANCE {
+            return rewrite(a.len(), b.len());
+        }
+        trace.push(v[index(-d)..=index(d)].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+                v[index(k + 1)]
+            } else {
+                v[index(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index(k)] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let at = |k: isize| v[(k + d) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = if d == 0 { 0 } else { at(prev_k) };
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            edits.push(Edit::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            edits.push(if x == prev_x {
+                Edit::Insert
+            } else {
+                Edit::Delete
+            });
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    edits.reverse();
+    edits
+}
+
+/// Every old line deleted and every new line added.
+fn rewrite(old_len: usize, new_len: usize) -> Vec<Edit> {
+    let mut edits = vec![Edit::Delete; old_len];
+    edits.extend(std::iter::repeat_n(Edit::Insert, new_len));
+    edits
+}
+
+/// Groups edits into `diff -u` style hunks, merging changes whose context would overlap.
+fn build_hunks(edits: &[Edit], old: &[&str], new: &[&str]) -> Vec<Hunk> {
+    // Lines of each side consumed before every edit.
+    let mut positions = Vec::with_capacity(edits.len() + 1);
+    let (mut old_pos, mut new_pos) = (0usize, 0usize);
+    for edit in edits {
+        positions.push((old_pos, new_pos));
+        match edit {
+            Edit::Equal => {
+                old_pos += 1;
+                new_pos += 1;
+            }
+            Edit::Delete => old_pos += 1,
+            Edit::Insert => new_pos += 1,
+        }
+    }
+    positions.push((old_pos, new_pos));
+
+    let changes: Vec<usize> = edits
+        .iter()
+        .enumerate()
+        .filter(|(_, edit)| **edit != Edit::Equal)
+        .map(|(index, _)| index)
+        .collect();
+
+    let mut groups: Vec<(usize, usize)> = Vec::new();
+    for index in changes {
+        match groups.last_mut() {
+            // Equal lines between two changes both hunks would show as context.
+            Some((_, last)) if index - *last - 1 <= 2 * CONTEXT_LINES => *last = index,
+            _ => groups.push((index, index)),
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(first, last)| {
+            let start = first.saturating_sub(CONTEXT_LINES);
+            let end = (last + 1 + CONTEXT_LINES).min(edits.len());
+            let (old_start, new_start) = positions[start];
+            let (old_end, new_end) = positions[end];
+            let mut hunk = Hunk {
+                old_start: hunk_start(old_start, old_end),
+                old_count: (old_end - old_start) as u32,
+                new_start: hunk_start(new_start, new_end),
+                new_count: (new_end - new_start) as u32,
+                section: None,
+                added: Vec::new(),
+                removed: Vec::new(),
+            };
+            for index in start..end {
+                let (old_pos, new_pos) = positions[index];
+                match edits[index] {
+                    Edit::Equal => {}
+                    Edit::Delete => hunk.removed.push(DiffLine {
+                        number: old_pos as u32 + 1,
+                        content: old[old_pos].to_string(),
+                    }),
+                    Edit::Insert => hunk.added.push(DiffLine {
+                        number: new_pos as u32 + 1,
+                        content: new[new_pos].to_string(),
+                    }),
+                }
+            }
+            hunk
+        })
+        .collect()
+}
+
+/// An empty side is written as the line before it, e.g. `-10,0`.
+fn hunk_start(start: usize, end: usize) -> u32 {
+    if end > start {
+        start as u32 + 1
+    } else {
+        start as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::diff_text;
+    use crate::diff::git::parse_unified_diff;
+    use std::io::Cursor;
+
+    #[test]
+    fn matches_unified_diff_output() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\nn\n";
+        let unified = "\
+--- a/f.txt
++++ b/f.txt
+@@ -1,5 +1,5 @@
+ a
+-b
++B
+ c
+ d
+ e
+@@ -11,3 +11,4 @@
+ k
+ l
+ m
++n
+";
+        let expected = parse_unified_diff(Cursor::new(unified)).expect("parse diff");
+        let actual = diff_text("f.txt", old, new).expect("changed");
+
+        assert_eq!(actual.path, expected[0].path);
+        assert_eq!(actual.changed_lines, expected[0].changed_lines);
+        assert_eq!(actual.hunks, expected[0].hunks);
+    }
+
+    #[test]
+    fn handles_new_and_identical_files() {
+        let added = diff_text("new.rs", "", "x\ny\n").expect("changed");
+        assert_eq!(added.changed_lines, vec![1, 2]);
+        assert_eq!((added.hunks[0].old_start, added.hunks[0].old_count), (0, 0));
+        assert!(diff_text("same.rs", "x\n", "x\n").is_none());
+    }
+
+    #[test]
+    fn treats_heavily_rewritten_files_as_replaced() {
+        let old: String = (0..3000)
+            .map(|line| format!("old {line}\nsame\n"))
+            .collect();
+        let new: String = (0..3000)
+            .map(|line| format!("new {line}\nsame\n"))
+            .collect();
+        let changed = diff_text("big.rs", &old, &new).expect("changed");
+        // The trailing `same` line is trimmed as a common suffix before the search.
+        assert_eq!(changed.changed_lines, (1..6000).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn finds_minimal_edits_in_the_middle() {
+        let old = "fn a() {}\nfn b() {}\nfn c() {}\n";
+        let new = "fn a() {}\nfn x() {}\nfn c() {}\nfn d() {}\n";
+        let file = diff_text("lib.rs", old, new).expect("changed");
+        assert_eq!(file.changed_lines, vec![2, 4]);
+        assert_eq!(file.hunks.len(), 1);
+        assert_eq!(file.hunks[0].removed[0].number, 2);
+    }
+}
@@ -1,5 +1,57 @@
 #[derive(Debug, Clone)]
 pub struct ChangedFile {
     pub path: String,
+    /// Previous path when the diff renames the file.
+    pub old_path: Option<String>,
     pub changed_lines: Vec<u32>,
+    pub hunks: Vec<Hunk>,
+}
+
+/// One patch of a series: its changed files and the paths it deletes.
+#[derive(Debug, Clone, Default)]
+pub struct Patch {
+    pub files: Vec<ChangedFile>,
+    pub deleted: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    pub old_start: u32,
+    pub old_count: u32,
+    pub new_start: u32,
+    pub new_count: u32,
+    /// Section heading git prints after the closing `@@`, usually the enclosing function.
+    pub section: Option<String>,
+    pub added: Vec<DiffLine>,
+    pub removed: Vec<DiffLine>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffLine {
+    /// Line number in the new file for added lines, in the old file for removed lines.
+    pub number: u32,
+    pub content: String,
+}
+
+impl ChangedFile {
+    pub fn new(path: impl Into<String>, changed_lines: Vec<u32>) -> Self {
+        Self {
+            path: path.into(),
+            old_path: None,
+            changed_lines,
+            hunks: Vec::new(),
+        }
+    }
+}
+
+impl Hunk {
+    pub fn new_end(&self) -> u32 {
+        self.new_start
+            .saturating_add(self.new_count)
+            .saturating_sub(1)
+    }
+
+    pub fn contains_new_line(&self, line: u32) -> bool {
+        self.new_count > 0 && line >= self.new_start && line <= self.new_end()
+    }
 }
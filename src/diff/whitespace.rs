@@ -0,0 +1,294 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use super::types::ChangedFile;
+
+/// Drops changed lines that are blank, comment-only, or that match a removed line of the same
+/// hunk once whitespace is ignored. Hunks keep every added line, so moved-code detection still
+/// sees whole blocks. Returns how many changed lines were excluded.
+pub fn exclude_whitespace_changes(files: &mut [ChangedFile]) -> usize {
+    let mut excluded_total = 0usize;
+
+    for file in files.iter_mut() {
+        let comments = CommentSyntax::for_path(&file.path);
+        let mut excluded: HashSet<u32> = HashSet::new();
+
+        for hunk in &file.hunks {
+            let mut removed: HashMap<String, usize> = HashMap::new();
+            for line in &hunk.removed {
+                *removed.entry(strip_whitespace(&line.content)).or_default() += 1;
+            }
+
+            let mut block = BlockState::default();
+            for line in &hunk.added {
+                let stripped = strip_whitespace(&line.content);
+                let in_comment = block.advance(&comments, line.number, &line.content);
+                let skip = if stripped.is_empty() || in_comment {
+                    true
+                } else if let Some(count) = removed.get_mut(&stripped).filter(|count| **count > 0) {
+                    *count -= 1;
+                    true
+                } else {
+                    false
+                };
+                if skip {
+                    excluded.insert(line.number);
+                }
+            }
+        }
+
+        if excluded.is_empty() {
+            continue;
+        }
+        let before = file.changed_lines.len();
+        file.changed_lines.retain(|line| !excluded.contains(line));
+        excluded_total += before - file.changed_lines.len();
+    }
+
+    excluded_total
+}
+
+fn strip_whitespace(content: &str) -> String {
+    content.chars().filter(|ch| !ch.is_whitespace()).collect()
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct CommentSyntax {
+    line: &'static [&'static str],
+    block: Option<(&'static str, &'static str)>,
+    /// Code that starts like a line comment, e.g. PHP 8 attributes (`#[Route(...)]`).
+    not_comments: &'static [&'static str],
+}
+
+impl CommentSyntax {
+    fn for_path(path: &str) -> Self {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase())
+            .unwrap_or_default();
+
+        match extension.as_str() {
+            "rs" | "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "hh" | "java" | "js" | "jsx"
+            | "mjs" | "cjs" | "ts" | "tsx" | "go" | "kt" | "kts" | "swift" | "cs" | "scala"
+            | "dart" | "groovy" | "m" | "mm" | "css" | "scss" | "less" => Self {
+                line: &["//"],
+                block: Some(("/*", "*/")),
+                not_comments: &[],
+            },
+            "php" => Self {
+                line: &["//", "#"],
+                block: Some(("/*", "*/")),
+                not_comments: &["#["],
+            },
+            "py" | "rb" | "sh" | "bash" | "zsh" | "pl" | "pm" | "r" | "ex" | "exs" | "yml"
+            | "yaml" | "toml" | "cmake" | "ps1" | "tf" | "coffee" => Self {
+                line: &["#"],
+                block: None,
+                not_comments: &[],
+            },
+            "sql" | "lua" | "hs" | "elm" | "ada" => Self {
+                line: &["--"],
+                block: None,
+                not_comments: &[],
+            },
+            "lisp" | "clj" | "cljs" | "el" | "scm" | "ini" | "asm" => Self {
+                line: &[";"],
+                block: None,
+                not_comments: &[],
+            },
+            "html" | "htm" | "xml" | "vue" | "svelte" | "md" => Self {
+                line: &[],
+                block: Some(("<!--", "-->")),
+                not_comments: &[],
+            },
+            _ => Self::default(),
+        }
+    }
+
+    fn is_line_comment(&self, trimmed: &str) -> bool {
+        self.line.iter().any(|prefix| trimmed.starts_with(prefix))
+            && !self
+                .not_comments
+                .iter()
+                .any(|prefix| trimmed.starts_with(prefix))
+    }
+}
+
+/// Tracks whether consecutive added lines sit inside a block comment opened by an added line.
+#[derive(Debug, Default)]
+struct BlockState {
+    open: bool,
+    last_line: Option<u32>,
+}
+
+impl BlockState {
+    /// Returns true when `content` at `number` is comment-only.
+    fn advance(&mut self, syntax: &CommentSyntax, number: u32, content: &str) -> bool {
+        if self.last_line.map(|last| last + 1) != Some(number) {
+            self.open = false;
+        }
+        self.last_line = Some(number);
+
+        let trimmed = content.trim();
+        if trimmed.is_empty() {
+            return false;
+        }
+        let Some((open, close)) = syntax.block else {
+            return syntax.is_line_comment(trimmed);
+        };
+
+        if self.open {
+            return match trimmed.find(close) {
+                Some(end) => {
+                    self.open = false;
+                    trimmed[end + close.len()..].trim().is_empty()
+                }
+                None => true,
+            };
+        }
+        if syntax.is_line_comment(trimmed) {
+            return true;
+        }
+        let Some(rest) = trimmed.strip_prefix(open) else {
+            return false;
+        };
+        match rest.find(close) {
+            // A block comment followed by code on the same line still counts as code.
+            Some(end) => rest[end + close.len()..].trim().is_empty(),
+            None => {
+                self.open = true;
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{exclude_whitespace_changes, BlockState, CommentSyntax};
+    use crate::diff::git::parse_unified_diff;
+    use crate::diff::moved::split_moved_lines;
+    use std::io::Cursor;
+
+    #[test]
+    fn excludes_reindented_blank_and_comment_lines() {
+        let diff = "\
+diff --git a/src/lib.rs b/src/lib.rs
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,3 +1,6 @@
+-fn compute() -> u32 {
+-  1 + 1
++fn compute() -> u32
++{
++    1 + 1
++
++    // keep it simple
++    * 2
+ }
+";
+        let mut files = parse_unified_diff(Cursor::new(diff)).expect("parse diff");
+        let excluded = exclude_whitespace_changes(&mut files);
+
+        assert_eq!(excluded, 3);
+        assert_eq!(files[0].changed_lines, vec![1, 2, 6]);
+        let added: Vec<u32> = files[0].hunks[0]
+            .added
+            .iter()
+            .map(|line| line.number)
+            .collect();
+        assert_eq!(added, vec![1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn keeps_moved_blocks_whole() {
+        let diff = "\
+diff --git a/src/lib.rs b/src/lib.rs
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,7 +1 @@
+ fn main() {}
+-fn helper() {
+-    let x = 1;
+-
+-    // double it
+-    x * 2
+-}
+@@ -20 +14,7 @@
+ fn tail() {}
++fn helper() {
++    let x = 1;
++
++    // double it
++    x * 2
++}
+";
+        let mut files = parse_unified_diff(Cursor::new(diff)).expect("parse diff");
+        assert_eq!(exclude_whitespace_changes(&mut files), 2);
+        let moved = split_moved_lines(&mut files, 3);
+
+        assert!(files[0].changed_lines.is_empty());
+        assert_eq!(moved[0].changed_lines, vec![15, 16, 19, 20]);
+    }
+
+    #[test]
+    fn pairs_each_removed_line_only_once() {
+        let diff = "\
+diff --git a/app.py b/app.py
+--- a/app.py
++++ b/app.py
+@@ -1 +1,2 @@
+-x = 1
++x  =  1
++x = 1
+";
+        let mut files = parse_unified_diff(Cursor::new(diff)).expect("parse diff");
+        let excluded = exclude_whitespace_changes(&mut files);
+
+        assert_eq!(excluded, 1);
+        assert_eq!(files[0].changed_lines, vec![2]);
+    }
+
+    #[test]
+    fn detects_comment_only_lines_per_language() {
+        let is_comment = |path: &str, lines: &[&str]| -> Vec<bool> {
+            let syntax = CommentSyntax::for_path(path);
+            let mut block = BlockState::default();
+            lines
+                .iter()
+                .zip(1u32..)
+                .map(|(line, number)| block.advance(&syntax, number, line))
+                .collect()
+        };
+
+        assert_eq!(
+            is_comment(
+                "src/lib.rs",
+                &[
+                    "    /// docs",
+                    "/* inline */",
+                    "/* inline */ call();",
+                    "*ptr = 1;"
+                ]
+            ),
+            vec![true, true, false, false]
+        );
+        assert_eq!(
+            is_comment("src/Foo.php", &["/**", " * Adds.", " */", "return 1;"]),
+            vec![true, true, true, false]
+        );
+        assert_eq!(
+            is_comment(
+                "src/Controller.php",
+                &["#[Route('/home')]", "# note", "public function home() {}"]
+            ),
+            vec![false, true, false]
+        );
+        assert_eq!(
+            is_comment("tool/app.py", &["  # note", "x = 1  # note"]),
+            vec![true, false]
+        );
+        assert_eq!(is_comment("LICENSE", &["# heading"]), vec![false]);
+    }
+}
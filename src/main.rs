@@ -4,6 +4,7 @@ mod coverage;
 mod diff;
 mod report;
 mod util;
+mod vcs;
 
 use owo_colors::OwoColorize;
 
@@ -2,7 +2,7 @@ use std::io::{BufWriter, Write};
 
 use owo_colors::OwoColorize;
 
-use super::{CoverageReport, ReportGenerator};
+use super::{CoverageReport, CoverageSection, ReportGenerator, UncoveredFile};
 
 pub fn render_to<W: Write + ?Sized>(
     report: &CoverageReport,
@@ -23,6 +23,24 @@ pub fn render_to<W: Write + ?Sized>(
         "Changed lines covered: {}/{}",
         report.total_covered, report.total_changed
     )?;
+    if report.project_lines > 0 {
+        writeln!(
+            out,
+            "Project lines covered: {}/{} ({:.2}%)",
+            report.project_covered,
+            report.project_lines,
+            report.project_coverage_percent()
+        )?;
+    }
+    if report.total_branches > 0 {
+        writeln!(
+            out,
+            "Changed branches covered: {}/{} ({:.2}%)",
+            report.covered_branches,
+            report.total_branches,
+            report.branch_coverage_percent()
+        )?;
+    }
 
     if report.uncovered_files.is_empty() {
         let message = "All changed lines are covered.";
@@ -34,27 +52,199 @@ pub fn render_to<W: Write + ?Sized>(
     } else {
         writeln!(out)?;
         writeln!(out, "Uncovered files:")?;
-        for file in &report.uncovered_files {
-            let percent = if file.changed_lines == 0 {
-                100.0
-            } else {
-                (file.covered_lines as f64 / file.changed_lines as f64) * 100.0
-            };
-            let percent_text = format!("{percent:.2}%");
-            let percent_display = if use_color {
-                colorize_percent(&percent_text, percent)
-            } else {
-                percent_text
+        write_uncovered_files(&mut out, &report.uncovered_files, use_color)?;
+    }
+
+    if !report.partial_branches.is_empty() {
+        writeln!(out, "Partially covered branches:")?;
+        for file in &report.partial_branches {
+            let lines: Vec<String> = file
+                .lines
+                .iter()
+                .map(|partial| format!("{} ({}/{})", partial.line, partial.covered, partial.total))
+                .collect();
+            writeln!(out, "{}: {}", file.path, lines.join(", "))?;
+        }
+    }
+    if !report.threshold_groups.is_empty() {
+        writeln!(out, "Thresholds:")?;
+        for group in &report.threshold_groups {
+            let status = if group.passed() { "ok" } else { "FAILED" };
+            let status = match (use_color, group.passed()) {
+                (true, true) => status.green().to_string(),
+                (true, false) => status.red().bold().to_string(),
+                (false, _) => status.to_string(),
             };
-            let lines = format_line_ranges(&file.uncovered_lines);
-            writeln!(out, "{} ({}): {}", file.path, percent_display, lines)?;
+            writeln!(
+                out,
+                "{}: {:.2}% ({}/{}), minimum {:.2}% {}",
+                group.pattern,
+                group.coverage_percent(),
+                group.total_covered,
+                group.total_changed,
+                group.threshold,
+                status
+            )?;
         }
     }
-
+    if !report.regressions.is_empty() {
+        writeln!(out, "Lines that lost coverage since base:")?;
+        for file in &report.regressions {
+            writeln!(out, "{}: {}", file.path, format_line_ranges(&file.lines))?;
+        }
+    }
+    if !report.uncalled_functions.is_empty() {
+        writeln!(out, "Changed functions never called:")?;
+        for function in &report.uncalled_functions {
+            writeln!(
+                out,
+                "{}:{} {}",
+                function.path, function.start_line, function.name
+            )?;
+        }
+    }
+    if !report.stale_signals.is_empty() {
+        writeln!(out, "Coverage may be stale:")?;
+        for signal in &report.stale_signals {
+            writeln!(out, "{signal}")?;
+        }
+    }
+    if !report.ambiguous_paths.is_empty() {
+        writeln!(out, "Ambiguous coverage paths:")?;
+        for ambiguous in &report.ambiguous_paths {
+            writeln!(
+                out,
+                "{}: used {} (matched {})",
+                ambiguous.path,
+                ambiguous.used.join(", "),
+                ambiguous.matches.join(", ")
+            )?;
+        }
+    }
+    if !report.skipped_files.is_empty() {
+        writeln!(out, "Skipped files:")?;
+        for file in &report.skipped_files {
+            writeln!(out, "{} ({})", file.path, file.reason)?;
+        }
+    }
+    if report.excluded_whitespace_lines > 0 {
+        writeln!(
+            out,
+            "Excluded whitespace-only, blank or comment lines: {}",
+            report.excluded_whitespace_lines
+        )?;
+    }
+    if report.attributed_lines > 0 {
+        writeln!(
+            out,
+            "Continuation lines counted with their statement: {}",
+            report.attributed_lines
+        )?;
+    }
+    if report.excluded_marked_lines > 0 {
+        writeln!(
+            out,
+            "Excluded lines marked as not covered: {}",
+            report.excluded_marked_lines
+        )?;
+    }
+    if let Some(moved) = &report.moved {
+        write_section(&mut out, "Moved lines", "moved lines", moved, use_color)?;
+    } else if report.moved_lines > 0 {
+        writeln!(out, "Excluded moved lines: {}", report.moved_lines)?;
+    }
+    if let Some(incremental) = &report.incremental {
+        write_section(
+            &mut out,
+            "Lines changed since last review",
+            "lines changed since last review",
+            incremental,
+            use_color,
+        )?;
+    }
     writeln!(out, "Coverage for changed lines: {percent_display}")?;
     out.flush()
 }
 
+fn write_section<W: Write>(
+    out: &mut W,
+    title: &str,
+    lines_label: &str,
+    section: &CoverageSection,
+    use_color: bool,
+) -> std::io::Result<()> {
+    let percent = section.coverage_percent();
+    let percent_text = format!("{percent:.2}%");
+    let percent_display = if use_color {
+        colorize_percent(&percent_text, percent)
+    } else {
+        percent_text
+    };
+    writeln!(
+        out,
+        "{title} covered: {}/{} ({percent_display})",
+        section.total_covered, section.total_changed
+    )?;
+    if !section.uncovered_files.is_empty() {
+        writeln!(out, "Uncovered {lines_label}:")?;
+        write_uncovered_files(out, &section.uncovered_files, use_color)?;
+    }
+    Ok(())
+}
+
+fn write_uncovered_files<W: Write>(
+    out: &mut W,
+    files: &[UncoveredFile],
+    use_color: bool,
+) -> std::io::Result<()> {
+    for file in files {
+        let percent = if file.changed_lines == 0 {
+            100.0
+        } else {
+            (file.covered_lines as f64 / file.changed_lines as f64) * 100.0
+        };
+        let percent_text = format!("{percent:.2}%");
+        let percent_display = if use_color {
+            colorize_percent(&percent_text, percent)
+        } else {
+            percent_text
+        };
+        if file.uncovered_lines.is_empty() {
+            writeln!(out, "{} ({})", file.path, percent_display)?;
+        } else {
+            let lines = format_line_ranges(&file.uncovered_lines);
+            writeln!(out, "{} ({}): {}", file.path, percent_display, lines)?;
+        }
+        for hunk in &file.hunks {
+            let lines = format_line_ranges(&hunk.uncovered_lines);
+            match hunk.section.as_deref() {
+                Some(section) => writeln!(
+                    out,
+                    "  @@ {}-{} @@ {}: {}",
+                    hunk.new_start, hunk.new_end, section, lines
+                )?,
+                None => writeln!(
+                    out,
+                    "  @@ {}-{} @@: {}",
+                    hunk.new_start, hunk.new_end, lines
+                )?,
+            }
+        }
+        if !file.low_hit_lines.is_empty() {
+            let lines: Vec<String> = file
+                .low_hit_lines
+                .iter()
+                .map(|low| {
+                    let unit = if low.hits == 1 { "hit" } else { "hits" };
+                    format!("{} ({} {unit})", low.line, low.hits)
+                })
+                .collect();
+            writeln!(out, "  below --min-hits: {}", lines.join(", "))?;
+        }
+    }
+    Ok(())
+}
+
 pub struct CliReportGenerator {
     pub use_color: bool,
 }
@@ -108,7 +298,12 @@ fn push_range(ranges: &mut Vec<String>, start: u32, end: u32) {
 
 #[cfg(test)]
 mod tests {
-    use super::format_line_ranges;
+    use super::{format_line_ranges, render_to};
+    use crate::report::{
+        AmbiguousPath, CoverageReport, CoverageSection, LowHitLine, PartialBranchFile,
+        PartialBranchLine, RegressedFile, SkippedFile, StaleSignal, ThresholdGroup,
+        UncalledFunction, UncoveredFile, UncoveredHunk,
+    };
 
     #[test]
     fn formats_line_ranges() {
@@ -117,4 +312,277 @@ mod tests {
         assert_eq!(format_line_ranges(&[1, 2, 3, 5]), "1-3, 5");
         assert_eq!(format_line_ranges(&[1, 3, 5, 6, 7, 8, 9]), "1, 3, 5-9");
     }
+
+    #[test]
+    fn renders_uncovered_hunks_with_section() {
+        let report = CoverageReport {
+            total_changed: 3,
+            total_covered: 1,
+            uncovered_files: vec![UncoveredFile {
+                path: "src/foo.rs".to_string(),
+                uncovered_lines: vec![4, 5],
+                covered_lines: 1,
+                changed_lines: 3,
+                low_hit_lines: Vec::new(),
+                hunks: vec![UncoveredHunk {
+                    new_start: 2,
+                    new_end: 8,
+                    section: Some("fn compute()".to_string()),
+                    uncovered_lines: vec![4, 5],
+                }],
+            }],
+            ..CoverageReport::default()
+        };
+
+        let mut out = Vec::new();
+        render_to(&report, &mut out, false).expect("render");
+        let content = String::from_utf8(out).expect("utf8");
+        assert!(content.contains("src/foo.rs (33.33%): 4-5\n"));
+        assert!(content.contains("  @@ 2-8 @@ fn compute(): 4-5\n"));
+        assert!(!content.contains("Excluded whitespace-only"));
+    }
+
+    #[test]
+    fn renders_excluded_whitespace_count() {
+        let report = CoverageReport {
+            total_changed: 1,
+            total_covered: 1,
+            excluded_whitespace_lines: 3,
+            excluded_marked_lines: 2,
+            attributed_lines: 4,
+            project_lines: 8,
+            project_covered: 6,
+            ..CoverageReport::default()
+        };
+
+        let mut out = Vec::new();
+        render_to(&report, &mut out, false).expect("render");
+        let content = String::from_utf8(out).expect("utf8");
+        assert!(content.contains("Excluded whitespace-only, blank or comment lines: 3\n"));
+        assert!(content.contains("Excluded lines marked as not covered: 2\n"));
+        assert!(content.contains("Project lines covered: 6/8 (75.00%)\n"));
+        assert!(content.contains("Continuation lines counted with their statement: 4\n"));
+    }
+
+    #[test]
+    fn renders_moved_lines_section() {
+        let report = CoverageReport {
+            total_changed: 2,
+            total_covered: 2,
+            moved_lines: 4,
+            moved: Some(CoverageSection {
+                total_changed: 4,
+                total_covered: 3,
+                uncovered_files: vec![UncoveredFile {
+                    path: "src/b.rs".to_string(),
+                    uncovered_lines: vec![5],
+                    covered_lines: 3,
+                    changed_lines: 4,
+                    low_hit_lines: Vec::new(),
+                    hunks: Vec::new(),
+                }],
+            }),
+            ..CoverageReport::default()
+        };
+
+        let mut out = Vec::new();
+        render_to(&report, &mut out, false).expect("render");
+        let content = String::from_utf8(out).expect("utf8");
+        assert!(content.contains("All changed lines are covered.\n"));
+        assert!(content.contains("Moved lines covered: 3/4 (75.00%)\n"));
+        assert!(content.contains("Uncovered moved lines:\nsrc/b.rs (75.00%): 5\n"));
+    }
+
+    #[test]
+    fn renders_incremental_review_section() {
+        let report = CoverageReport {
+            total_changed: 10,
+            total_covered: 8,
+            incremental: Some(CoverageSection {
+                total_changed: 2,
+                total_covered: 2,
+                uncovered_files: Vec::new(),
+            }),
+            ..CoverageReport::default()
+        };
+
+        let mut out = Vec::new();
+        render_to(&report, &mut out, false).expect("render");
+        let content = String::from_utf8(out).expect("utf8");
+        assert!(content.contains("Lines changed since last review covered: 2/2 (100.00%)\n"));
+        assert!(!content.contains("Uncovered lines changed since last review:"));
+    }
+
+    #[test]
+    fn renders_skipped_files() {
+        let report = CoverageReport {
+            skipped_files: vec![SkippedFile {
+                path: "api/schema.pb.go".to_string(),
+                reason: "linguist-generated".to_string(),
+            }],
+            ..CoverageReport::default()
+        };
+
+        let mut out = Vec::new();
+        render_to(&report, &mut out, false).expect("render");
+        let content = String::from_utf8(out).expect("utf8");
+        assert!(content.contains("Skipped files:\napi/schema.pb.go (linguist-generated)\n"));
+    }
+
+    #[test]
+    fn renders_branch_totals_and_partial_lines() {
+        let report = CoverageReport {
+            total_changed: 2,
+            total_covered: 2,
+            total_branches: 4,
+            covered_branches: 3,
+            partial_branches: vec![PartialBranchFile {
+                path: "src/a.rs".to_string(),
+                lines: vec![PartialBranchLine {
+                    line: 7,
+                    covered: 1,
+                    total: 2,
+                }],
+            }],
+            ..CoverageReport::default()
+        };
+
+        let mut out = Vec::new();
+        render_to(&report, &mut out, false).expect("render");
+        let content = String::from_utf8(out).expect("utf8");
+        assert!(content.contains("Changed branches covered: 3/4 (75.00%)\n"));
+        assert!(content.contains(
+            "All changed lines are covered.\nPartially covered branches:\nsrc/a.rs: 7 (1/2)\n"
+        ));
+    }
+
+    #[test]
+    fn renders_uncalled_functions() {
+        let report = CoverageReport {
+            uncalled_functions: vec![UncalledFunction {
+                path: "src/Calculator.php".to_string(),
+                name: "add".to_string(),
+                start_line: 14,
+            }],
+            ..CoverageReport::default()
+        };
+
+        let mut out = Vec::new();
+        render_to(&report, &mut out, false).expect("render");
+        let content = String::from_utf8(out).expect("utf8");
+        assert!(content.contains("Changed functions never called:\nsrc/Calculator.php:14 add\n"));
+    }
+
+    #[test]
+    fn renders_ambiguous_paths() {
+        let report = CoverageReport {
+            ambiguous_paths: vec![AmbiguousPath {
+                path: "utils.py".to_string(),
+                matches: vec!["a/utils.py".to_string(), "b/utils.py".to_string()],
+                used: vec!["a/utils.py".to_string()],
+            }],
+            ..CoverageReport::default()
+        };
+
+        let mut out = Vec::new();
+        render_to(&report, &mut out, false).expect("render");
+        let content = String::from_utf8(out).expect("utf8");
+        assert!(content.contains(
+            "Ambiguous coverage paths:\nutils.py: used a/utils.py (matched a/utils.py, b/utils.py)\n"
+        ));
+    }
+
+    #[test]
+    fn renders_threshold_groups() {
+        let report = CoverageReport {
+            threshold_groups: vec![
+                ThresholdGroup {
+                    pattern: "src/core/**".to_string(),
+                    threshold: 90.0,
+                    total_changed: 4,
+                    total_covered: 3,
+                },
+                ThresholdGroup {
+                    pattern: "src/cli/**".to_string(),
+                    threshold: 60.0,
+                    total_changed: 0,
+                    total_covered: 0,
+                },
+            ],
+            ..CoverageReport::default()
+        };
+
+        let mut out = Vec::new();
+        render_to(&report, &mut out, false).expect("render");
+        let content = String::from_utf8(out).expect("utf8");
+        assert!(content.contains(
+            "Thresholds:\nsrc/core/**: 75.00% (3/4), minimum 90.00% FAILED\nsrc/cli/**: 100.00% (0/0), minimum 60.00% ok\n"
+        ));
+    }
+
+    #[test]
+    fn renders_lines_below_min_hits() {
+        let report = CoverageReport {
+            total_changed: 2,
+            uncovered_files: vec![UncoveredFile {
+                path: "src/a.rs".to_string(),
+                uncovered_lines: vec![3, 4],
+                covered_lines: 0,
+                changed_lines: 2,
+                hunks: Vec::new(),
+                low_hit_lines: vec![
+                    LowHitLine { line: 3, hits: 1 },
+                    LowHitLine { line: 4, hits: 2 },
+                ],
+            }],
+            ..CoverageReport::default()
+        };
+
+        let mut out = Vec::new();
+        render_to(&report, &mut out, false).expect("render");
+        let content = String::from_utf8(out).expect("utf8");
+        assert!(
+            content.contains("src/a.rs (0.00%): 3-4\n  below --min-hits: 3 (1 hit), 4 (2 hits)\n")
+        );
+    }
+
+    #[test]
+    fn renders_regressions() {
+        let report = CoverageReport {
+            regressions: vec![RegressedFile {
+                path: "src/lib.rs".to_string(),
+                lines: vec![5, 6, 9],
+            }],
+            ..CoverageReport::default()
+        };
+
+        let mut out = Vec::new();
+        render_to(&report, &mut out, false).expect("render");
+        let content = String::from_utf8(out).expect("utf8");
+        assert!(content.contains("Lines that lost coverage since base:\nsrc/lib.rs: 5-6, 9\n"));
+    }
+
+    #[test]
+    fn renders_stale_signals() {
+        let report = CoverageReport {
+            stale_signals: vec![
+                StaleSignal {
+                    path: None,
+                    reason: "coverage was generated 3 day(s) before the HEAD commit".to_string(),
+                },
+                StaleSignal {
+                    path: Some("src/lib.rs".to_string()),
+                    reason: "line 40 is measured but the file has 12 lines".to_string(),
+                },
+            ],
+            ..CoverageReport::default()
+        };
+
+        let mut out = Vec::new();
+        render_to(&report, &mut out, false).expect("render");
+        let content = String::from_utf8(out).expect("utf8");
+        assert!(content.contains(
+            "Coverage may be stale:\ncoverage was generated 3 day(s) before the HEAD commit\nsrc/lib.rs: line 40 is measured but the file has 12 lines\n"
+        ));
+    }
 }
@@ -33,8 +33,20 @@ pub fn render_report(report: &CoverageReport) -> Result<String, String> {
 
     for file in &report.uncovered_files {
         for line in &file.uncovered_lines {
+            let section = file
+                .hunks
+                .iter()
+                .find(|hunk| hunk.uncovered_lines.contains(line))
+                .and_then(|hunk| hunk.section.as_deref());
+            let mut description = match section {
+                Some(section) => format!("Uncovered changed line {line} in `{section}`."),
+                None => format!("Uncovered changed line {line}."),
+            };
+            if let Some(low) = file.low_hit_lines.iter().find(|low| low.line == *line) {
+                description.push_str(&format!(" It ran {} time(s), below --min-hits.", low.hits));
+            }
             issues.push(CodeQualityIssue {
-                description: format!("Uncovered changed line {line}."),
+                description,
                 fingerprint: fingerprint_for(&file.path, *line),
                 severity: "minor".to_string(),
                 location: IssueLocation {
@@ -47,6 +59,78 @@ pub fn render_report(report: &CoverageReport) -> Result<String, String> {
             });
         }
     }
+    for file in &report.partial_branches {
+        for partial in &file.lines {
+            issues.push(CodeQualityIssue {
+                description: format!(
+                    "Changed line {} has partially covered branches ({}/{}).",
+                    partial.line, partial.covered, partial.total
+                ),
+                fingerprint: fingerprint_for(&file.path, partial.line),
+                severity: "info".to_string(),
+                location: IssueLocation {
+                    path: file.path.clone(),
+                    lines: IssueLines {
+                        begin: partial.line,
+                        end: partial.line,
+                    },
+                },
+            });
+        }
+    }
+
+    for file in &report.regressions {
+        for line in &file.lines {
+            issues.push(CodeQualityIssue {
+                description: format!(
+                    "Line {line} was covered in the base coverage report and is no longer covered."
+                ),
+                // Keyed separately from uncovered changed lines at the same spot.
+                fingerprint: fingerprint_for(&format!("{}#regression", file.path), *line),
+                severity: "major".to_string(),
+                location: IssueLocation {
+                    path: file.path.clone(),
+                    lines: IssueLines {
+                        begin: *line,
+                        end: *line,
+                    },
+                },
+            });
+        }
+    }
+    for function in &report.uncalled_functions {
+        issues.push(CodeQualityIssue {
+            description: format!("Changed function `{}` is never called.", function.name),
+            // Keyed by name so it does not collide with the uncovered line at the same spot.
+            fingerprint: fingerprint_for(
+                &format!("{}::{}", function.path, function.name),
+                function.start_line,
+            ),
+            severity: "major".to_string(),
+            location: IssueLocation {
+                path: function.path.clone(),
+                lines: IssueLines {
+                    begin: function.start_line,
+                    end: function.start_line,
+                },
+            },
+        });
+    }
+    for ambiguous in &report.ambiguous_paths {
+        issues.push(CodeQualityIssue {
+            description: format!(
+                "Several coverage files match this file ({}); used {}.",
+                ambiguous.matches.join(", "),
+                ambiguous.used.join(", ")
+            ),
+            fingerprint: fingerprint_for(&format!("{}#ambiguous", ambiguous.path), 1),
+            severity: "info".to_string(),
+            location: IssueLocation {
+                path: ambiguous.path.clone(),
+                lines: IssueLines { begin: 1, end: 1 },
+            },
+        });
+    }
 
     let mut payload = serde_json::to_string_pretty(&issues).map_err(|err| err.to_string())?;
     payload.push('\n');
@@ -72,7 +156,7 @@ fn fingerprint_for(path: &str, line: u32) -> String {
 mod tests {
     use super::GitlabReportGenerator;
     use crate::report::ReportGenerator;
-    use crate::report::{CoverageReport, UncoveredFile};
+    use crate::report::{AmbiguousPath, CoverageReport, UncoveredFile};
     use serde_json::Value;
 
     #[test]
@@ -85,7 +169,10 @@ mod tests {
                 uncovered_lines: vec![3, 7],
                 covered_lines: 0,
                 changed_lines: 2,
+                low_hit_lines: Vec::new(),
+                hunks: Vec::new(),
             }],
+            ..CoverageReport::default()
         };
 
         let mut out = Vec::new();
@@ -133,4 +220,29 @@ mod tests {
         assert_eq!(lines.get("begin").and_then(Value::as_u64), Some(3));
         assert_eq!(lines.get("end").and_then(Value::as_u64), Some(3));
     }
+
+    #[test]
+    fn reports_ambiguous_paths() {
+        let report = CoverageReport {
+            ambiguous_paths: vec![AmbiguousPath {
+                path: "utils.py".to_string(),
+                matches: vec!["a/utils.py".to_string(), "b/utils.py".to_string()],
+                used: vec!["a/utils.py".to_string()],
+            }],
+            ..CoverageReport::default()
+        };
+
+        let mut out = Vec::new();
+        GitlabReportGenerator
+            .write_report(&report, &mut out)
+            .expect("write report");
+        let payload: Vec<Value> = serde_json::from_slice(&out).expect("parse json");
+
+        assert_eq!(payload.len(), 1);
+        assert_eq!(payload[0]["location"]["path"], "utils.py");
+        assert_eq!(
+            payload[0]["description"],
+            "Several coverage files match this file (a/utils.py, b/utils.py); used a/utils.py."
+        );
+    }
 }
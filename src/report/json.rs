@@ -2,10 +2,79 @@ use std::io::Write;
 
 use serde::Serialize;
 
-use super::{CoverageReport, ReportGenerator};
+use super::{CoverageReport, CoverageSection, ReportGenerator, UncoveredFile};
 
 #[derive(Serialize)]
 struct JsonReport {
+    total_changed: usize,
+    total_covered: usize,
+    coverage_percent: f64,
+    total_branches: usize,
+    covered_branches: usize,
+    branch_coverage_percent: f64,
+    partial_branches: Vec<JsonPartialBranchFile>,
+    project_lines: usize,
+    project_covered: usize,
+    project_coverage_percent: f64,
+    excluded_whitespace_lines: usize,
+    excluded_marked_lines: usize,
+    attributed_lines: usize,
+    moved_lines: usize,
+    moved: Option<JsonSection>,
+    incremental: Option<JsonSection>,
+    skipped_files: Vec<JsonSkippedFile>,
+    uncalled_functions: Vec<JsonFunction>,
+    ambiguous_paths: Vec<JsonAmbiguousPath>,
+    thresholds: Vec<JsonThreshold>,
+    regressions: Vec<JsonRegression>,
+    stale_signals: Vec<JsonStaleSignal>,
+    uncovered_files: Vec<JsonFile>,
+}
+
+#[derive(Serialize)]
+struct JsonRegression {
+    path: String,
+    lines: Vec<u32>,
+}
+
+#[derive(Serialize)]
+struct JsonThreshold {
+    pattern: String,
+    threshold: f64,
+    total_changed: usize,
+    total_covered: usize,
+    coverage_percent: f64,
+    passed: bool,
+}
+
+#[derive(Serialize)]
+struct JsonStaleSignal {
+    path: Option<String>,
+    reason: String,
+}
+
+#[derive(Serialize)]
+struct JsonAmbiguousPath {
+    path: String,
+    matches: Vec<String>,
+    used: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct JsonFunction {
+    path: String,
+    name: String,
+    start_line: u32,
+}
+
+#[derive(Serialize)]
+struct JsonSkippedFile {
+    path: String,
+    reason: String,
+}
+
+#[derive(Serialize)]
+struct JsonSection {
     total_changed: usize,
     total_covered: usize,
     coverage_percent: f64,
@@ -16,6 +85,35 @@ struct JsonReport {
 struct JsonFile {
     path: String,
     uncovered_lines: Vec<u32>,
+    hunks: Vec<JsonHunk>,
+    low_hit_lines: Vec<JsonLowHitLine>,
+}
+
+#[derive(Serialize)]
+struct JsonLowHitLine {
+    line: u32,
+    hits: u32,
+}
+
+#[derive(Serialize)]
+struct JsonPartialBranchFile {
+    path: String,
+    lines: Vec<JsonPartialBranch>,
+}
+
+#[derive(Serialize)]
+struct JsonPartialBranch {
+    line: u32,
+    covered: u32,
+    total: u32,
+}
+
+#[derive(Serialize)]
+struct JsonHunk {
+    new_start: u32,
+    new_end: u32,
+    section: Option<String>,
+    uncovered_lines: Vec<u32>,
 }
 
 pub struct JsonReportGenerator;
@@ -28,31 +126,142 @@ impl ReportGenerator for JsonReportGenerator {
     }
 }
 
+fn json_section(section: &CoverageSection) -> JsonSection {
+    JsonSection {
+        total_changed: section.total_changed,
+        total_covered: section.total_covered,
+        coverage_percent: section.coverage_percent(),
+        uncovered_files: json_files(&section.uncovered_files),
+    }
+}
+
 fn render_report(report: &CoverageReport) -> Result<String, String> {
-    let uncovered_files = report
-        .uncovered_files
-        .iter()
-        .map(|file| JsonFile {
-            path: file.path.clone(),
-            uncovered_lines: file.uncovered_lines.clone(),
-        })
-        .collect();
     let payload = JsonReport {
         total_changed: report.total_changed,
         total_covered: report.total_covered,
         coverage_percent: report.coverage_percent(),
-        uncovered_files,
+        total_branches: report.total_branches,
+        covered_branches: report.covered_branches,
+        branch_coverage_percent: report.branch_coverage_percent(),
+        partial_branches: report
+            .partial_branches
+            .iter()
+            .map(|file| JsonPartialBranchFile {
+                path: file.path.clone(),
+                lines: file
+                    .lines
+                    .iter()
+                    .map(|partial| JsonPartialBranch {
+                        line: partial.line,
+                        covered: partial.covered,
+                        total: partial.total,
+                    })
+                    .collect(),
+            })
+            .collect(),
+        project_lines: report.project_lines,
+        project_covered: report.project_covered,
+        project_coverage_percent: report.project_coverage_percent(),
+        excluded_whitespace_lines: report.excluded_whitespace_lines,
+        excluded_marked_lines: report.excluded_marked_lines,
+        attributed_lines: report.attributed_lines,
+        moved_lines: report.moved_lines,
+        moved: report.moved.as_ref().map(json_section),
+        incremental: report.incremental.as_ref().map(json_section),
+        skipped_files: report
+            .skipped_files
+            .iter()
+            .map(|file| JsonSkippedFile {
+                path: file.path.clone(),
+                reason: file.reason.clone(),
+            })
+            .collect(),
+        uncalled_functions: report
+            .uncalled_functions
+            .iter()
+            .map(|function| JsonFunction {
+                path: function.path.clone(),
+                name: function.name.clone(),
+                start_line: function.start_line,
+            })
+            .collect(),
+        ambiguous_paths: report
+            .ambiguous_paths
+            .iter()
+            .map(|ambiguous| JsonAmbiguousPath {
+                path: ambiguous.path.clone(),
+                matches: ambiguous.matches.clone(),
+                used: ambiguous.used.clone(),
+            })
+            .collect(),
+        thresholds: report
+            .threshold_groups
+            .iter()
+            .map(|group| JsonThreshold {
+                pattern: group.pattern.clone(),
+                threshold: group.threshold,
+                total_changed: group.total_changed,
+                total_covered: group.total_covered,
+                coverage_percent: group.coverage_percent(),
+                passed: group.passed(),
+            })
+            .collect(),
+        regressions: report
+            .regressions
+            .iter()
+            .map(|file| JsonRegression {
+                path: file.path.clone(),
+                lines: file.lines.clone(),
+            })
+            .collect(),
+        stale_signals: report
+            .stale_signals
+            .iter()
+            .map(|signal| JsonStaleSignal {
+                path: signal.path.clone(),
+                reason: signal.reason.clone(),
+            })
+            .collect(),
+        uncovered_files: json_files(&report.uncovered_files),
     };
     let mut text = serde_json::to_string_pretty(&payload).map_err(|err| err.to_string())?;
     text.push('\n');
     Ok(text)
 }
 
+fn json_files(files: &[UncoveredFile]) -> Vec<JsonFile> {
+    files
+        .iter()
+        .map(|file| JsonFile {
+            path: file.path.clone(),
+            uncovered_lines: file.uncovered_lines.clone(),
+            hunks: file
+                .hunks
+                .iter()
+                .map(|hunk| JsonHunk {
+                    new_start: hunk.new_start,
+                    new_end: hunk.new_end,
+                    section: hunk.section.clone(),
+                    uncovered_lines: hunk.uncovered_lines.clone(),
+                })
+                .collect(),
+            low_hit_lines: file
+                .low_hit_lines
+                .iter()
+                .map(|low| JsonLowHitLine {
+                    line: low.line,
+                    hits: low.hits,
+                })
+                .collect(),
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::JsonReportGenerator;
     use crate::report::ReportGenerator;
-    use crate::report::{CoverageReport, UncoveredFile};
+    use crate::report::{CoverageReport, UncoveredFile, UncoveredHunk};
     use serde_json::Value;
 
     #[test]
@@ -65,7 +274,15 @@ mod tests {
                 uncovered_lines: vec![2, 3],
                 covered_lines: 2,
                 changed_lines: 4,
+                low_hit_lines: Vec::new(),
+                hunks: vec![UncoveredHunk {
+                    new_start: 1,
+                    new_end: 4,
+                    section: Some("fn foo()".to_string()),
+                    uncovered_lines: vec![2, 3],
+                }],
             }],
+            ..CoverageReport::default()
         };
 
         let mut out = Vec::new();
@@ -79,7 +296,17 @@ mod tests {
         assert_eq!(payload["total_changed"], 4);
         assert_eq!(payload["total_covered"], 2);
         assert!(payload["coverage_percent"].as_f64().is_some());
+        assert_eq!(payload["total_branches"], 0);
+        assert_eq!(payload["excluded_whitespace_lines"], 0);
+        assert_eq!(payload["excluded_marked_lines"], 0);
+        assert_eq!(payload["moved_lines"], 0);
+        assert!(payload["moved"].is_null());
         assert_eq!(payload["uncovered_files"][0]["path"], "src/foo.rs");
         assert_eq!(payload["uncovered_files"][0]["uncovered_lines"][0], 2);
+        assert_eq!(
+            payload["uncovered_files"][0]["hunks"][0]["section"],
+            "fn foo()"
+        );
+        assert_eq!(payload["uncovered_files"][0]["hunks"][0]["new_start"], 1);
     }
 }
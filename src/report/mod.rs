@@ -3,6 +3,7 @@ pub mod gitlab_code_quality;
 pub mod json;
 pub mod summary;
 
+use std::fmt;
 use std::io::Write;
 
 pub use console::CliReportGenerator;
@@ -10,11 +11,105 @@ pub use gitlab_code_quality::GitlabReportGenerator;
 pub use json::JsonReportGenerator;
 pub use summary::SummaryReportGenerator;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct CoverageReport {
     pub total_changed: usize,
     pub total_covered: usize,
     pub uncovered_files: Vec<UncoveredFile>,
+    /// Branches on measured changed lines, for reports that carry branch data.
+    pub total_branches: usize,
+    pub covered_branches: usize,
+    /// Changed files with covered lines whose branches were only partly taken.
+    pub partial_branches: Vec<PartialBranchFile>,
+    /// Added lines skipped by `--ignore-whitespace`.
+    pub excluded_whitespace_lines: usize,
+    /// Changed lines excluded by `pragma: no cover`, `LCOV_EXCL_*` and similar markers.
+    pub excluded_marked_lines: usize,
+    /// Added lines detected as moved code and kept out of the gate.
+    pub moved_lines: usize,
+    /// Coverage of moved lines when `--moved-lines separate` is used.
+    pub moved: Option<CoverageSection>,
+    /// Coverage of the lines changed since `--reviewed-head`.
+    pub incremental: Option<CoverageSection>,
+    /// Changed files left out of the analysis, e.g. generated or vendored code.
+    pub skipped_files: Vec<SkippedFile>,
+    /// Functions overlapping changed lines that no test entered.
+    pub uncalled_functions: Vec<UncalledFunction>,
+    /// Changed files that matched several coverage files.
+    pub ambiguous_paths: Vec<AmbiguousPath>,
+    /// Results of the `--threshold` rules, one per rule.
+    pub threshold_groups: Vec<ThresholdGroup>,
+    /// Lines covered in `--base-coverage` that lost coverage, in head line numbers.
+    pub regressions: Vec<RegressedFile>,
+    /// Unmeasured changed lines counted with the statement they continue.
+    pub attributed_lines: usize,
+    /// Measured and covered lines across every file in the coverage reports.
+    pub project_lines: usize,
+    pub project_covered: usize,
+    /// Signs that the coverage reports were produced from older sources.
+    pub stale_signals: Vec<StaleSignal>,
+}
+
+/// A hint that the coverage reports do not match the sources.
+#[derive(Debug)]
+pub struct StaleSignal {
+    /// The changed file the hint was found in; `None` for report-wide hints.
+    pub path: Option<String>,
+    pub reason: String,
+}
+
+impl fmt::Display for StaleSignal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "{path}: {}", self.reason),
+            None => f.write_str(&self.reason),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RegressedFile {
+    pub path: String,
+    pub lines: Vec<u32>,
+}
+
+/// Changed lines of the files assigned to one `--threshold` rule.
+#[derive(Debug)]
+pub struct ThresholdGroup {
+    pub pattern: String,
+    pub threshold: f64,
+    pub total_changed: usize,
+    pub total_covered: usize,
+}
+
+/// Coverage for a subset of changed lines, reported next to the main numbers.
+#[derive(Debug, Default)]
+pub struct CoverageSection {
+    pub total_changed: usize,
+    pub total_covered: usize,
+    pub uncovered_files: Vec<UncoveredFile>,
+}
+
+#[derive(Debug)]
+pub struct SkippedFile {
+    pub path: String,
+    pub reason: String,
+}
+
+#[derive(Debug)]
+pub struct AmbiguousPath {
+    pub path: String,
+    /// Every coverage file matching the path.
+    pub matches: Vec<String>,
+    /// The matches whose coverage was used.
+    pub used: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct UncalledFunction {
+    pub path: String,
+    pub name: String,
+    pub start_line: u32,
 }
 
 #[derive(Debug)]
@@ -23,18 +118,91 @@ pub struct UncoveredFile {
     pub uncovered_lines: Vec<u32>,
     pub covered_lines: usize,
     pub changed_lines: usize,
+    pub hunks: Vec<UncoveredHunk>,
+    /// Uncovered lines that ran, but fewer times than `--min-hits`.
+    pub low_hit_lines: Vec<LowHitLine>,
+}
+
+#[derive(Debug)]
+pub struct LowHitLine {
+    pub line: u32,
+    pub hits: u32,
+}
+
+/// Covered changed lines of one file where only some of the branches were taken.
+#[derive(Debug)]
+pub struct PartialBranchFile {
+    pub path: String,
+    pub lines: Vec<PartialBranchLine>,
+}
+
+#[derive(Debug)]
+pub struct PartialBranchLine {
+    pub line: u32,
+    pub covered: u32,
+    pub total: u32,
+}
+
+#[derive(Debug)]
+pub struct UncoveredHunk {
+    pub new_start: u32,
+    pub new_end: u32,
+    pub section: Option<String>,
+    pub uncovered_lines: Vec<u32>,
+}
+
+impl ThresholdGroup {
+    pub fn coverage_percent(&self) -> f64 {
+        percent_of(self.total_covered, self.total_changed)
+    }
+
+    pub fn passed(&self) -> bool {
+        self.coverage_percent() >= self.threshold
+    }
 }
 
 impl CoverageReport {
     pub fn coverage_percent(&self) -> f64 {
-        if self.total_changed == 0 {
-            100.0
-        } else {
-            (self.total_covered as f64 / self.total_changed as f64) * 100.0
+        percent_of(self.total_covered, self.total_changed)
+    }
+
+    pub fn project_coverage_percent(&self) -> f64 {
+        percent_of(self.project_covered, self.project_lines)
+    }
+
+    pub fn regressed_lines(&self) -> usize {
+        self.regressions.iter().map(|file| file.lines.len()).sum()
+    }
+
+    pub fn branch_coverage_percent(&self) -> f64 {
+        percent_of(self.covered_branches, self.total_branches)
+    }
+}
+
+impl CoverageSection {
+    pub fn coverage_percent(&self) -> f64 {
+        percent_of(self.total_covered, self.total_changed)
+    }
+}
+
+impl From<CoverageReport> for CoverageSection {
+    fn from(report: CoverageReport) -> Self {
+        Self {
+            total_changed: report.total_changed,
+            total_covered: report.total_covered,
+            uncovered_files: report.uncovered_files,
         }
     }
 }
 
+fn percent_of(covered: usize, total: usize) -> f64 {
+    if total == 0 {
+        100.0
+    } else {
+        (covered as f64 / total as f64) * 100.0
+    }
+}
+
 pub trait ReportGenerator {
     fn write_report(&self, report: &CoverageReport, out: &mut dyn Write) -> Result<(), String>;
 }
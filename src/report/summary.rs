@@ -21,6 +21,74 @@ pub fn render_to<W: Write + ?Sized>(
         "Summary: {percent_display} ({}/{}) changed lines covered",
         report.total_covered, report.total_changed
     )?;
+    if report.project_lines > 0 {
+        writeln!(
+            out,
+            "Project: {:.2}% ({}/{}) lines covered",
+            report.project_coverage_percent(),
+            report.project_covered,
+            report.project_lines
+        )?;
+    }
+    if report.total_branches > 0 {
+        writeln!(
+            out,
+            "Branches: {:.2}% ({}/{}) changed branches covered",
+            report.branch_coverage_percent(),
+            report.covered_branches,
+            report.total_branches
+        )?;
+    }
+    for group in &report.threshold_groups {
+        writeln!(
+            out,
+            "Threshold {}: {:.2}% ({}/{}), minimum {:.2}%",
+            group.pattern,
+            group.coverage_percent(),
+            group.total_covered,
+            group.total_changed,
+            group.threshold
+        )?;
+    }
+    if !report.regressions.is_empty() {
+        writeln!(
+            out,
+            "Regressions: {} line(s) lost coverage since base",
+            report.regressed_lines()
+        )?;
+    }
+    if !report.stale_signals.is_empty() {
+        writeln!(
+            out,
+            "Stale coverage: {} sign(s) the coverage predates the sources",
+            report.stale_signals.len()
+        )?;
+    }
+    for ambiguous in &report.ambiguous_paths {
+        writeln!(
+            out,
+            "Ambiguous path {}: used {} (matched {})",
+            ambiguous.path,
+            ambiguous.used.join(", "),
+            ambiguous.matches.join(", ")
+        )?;
+    }
+    if !report.uncalled_functions.is_empty() {
+        writeln!(
+            out,
+            "Functions: {} changed function(s) never called",
+            report.uncalled_functions.len()
+        )?;
+    }
+    if let Some(incremental) = &report.incremental {
+        writeln!(
+            out,
+            "Since last review: {:.2}% ({}/{}) changed lines covered",
+            incremental.coverage_percent(),
+            incremental.total_covered,
+            incremental.total_changed
+        )?;
+    }
 
     Ok(())
 }
@@ -0,0 +1,147 @@
+/// Matches a path against a gitignore-style glob: `*` and `?` stay within one path component,
+/// `**` spans directories and `[a-z]`/`[!a-z]` match character classes.
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let path: Vec<char> = path.chars().collect();
+    match_from(&pattern, &path)
+}
+
+/// Patterns without a slash match the file name at any depth; others are anchored to the start
+/// of `path`, as in `.gitattributes`.
+pub fn path_matches(pattern: &str, path: &str) -> bool {
+    let anchored = pattern.strip_prefix('/').unwrap_or(pattern);
+    if anchored.contains('/') {
+        glob_match(anchored, path)
+    } else {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        glob_match(anchored, name)
+    }
+}
+
+/// Like `path_matches`, but a pattern naming a directory also matches everything below it, as in
+/// `.gitignore`: `vendor` and `vendor/` both match `lib/vendor/x.go`. A trailing `/` only matches
+/// directories.
+pub fn path_or_directory_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, directory_only) = match pattern.strip_suffix('/') {
+        Some(directory) => (directory, true),
+        None => (pattern, false),
+    };
+    (!directory_only && path_matches(pattern, path))
+        || path
+            .match_indices('/')
+            .any(|(index, _)| path_matches(pattern, &path[..index]))
+}
+
+fn match_from(pattern: &[char], path: &[char]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        ['*', '*', '/', rest @ ..] => {
+            match_from(rest, path)
+                || path
+                    .iter()
+                    .enumerate()
+                    .any(|(index, ch)| *ch == '/' && match_from(rest, &path[index + 1..]))
+        }
+        ['*', '*', rest @ ..] => (0..=path.len()).any(|index| match_from(rest, &path[index..])),
+        ['*', rest @ ..] => {
+            let component = path.iter().position(|ch| *ch == '/').unwrap_or(path.len());
+            (0..=component).any(|index| match_from(rest, &path[index..]))
+        }
+        ['?', rest @ ..] => {
+            matches!(path.first(), Some(ch) if *ch != '/') && match_from(rest, &path[1..])
+        }
+        ['[', rest @ ..] => match (path.first(), match_class(rest)) {
+            (Some(ch), Some((class, negated, after))) if *ch != '/' => {
+                class_contains(class, *ch) != negated && match_from(after, &path[1..])
+            }
+            // An unterminated `[` is a literal.
+            (Some('['), None) => match_from(rest, &path[1..]),
+            _ => false,
+        },
+        ['\\', literal, rest @ ..] => path.first() == Some(literal) && match_from(rest, &path[1..]),
+        [literal, rest @ ..] => path.first() == Some(literal) && match_from(rest, &path[1..]),
+    }
+}
+
+/// Splits `a-z]rest` into the class body, whether it is negated, and the pattern after it.
+fn match_class(pattern: &[char]) -> Option<(&[char], bool, &[char])> {
+    let (negated, body) = match pattern.first() {
+        Some('!' | '^') => (true, &pattern[1..]),
+        _ => (false, pattern),
+    };
+    // A `]` right after the opening bracket is part of the class.
+    let end = body
+        .iter()
+        .skip(1)
+        .position(|ch| *ch == ']')
+        .map(|index| index + 1)?;
+    Some((&body[..end], negated, &body[end + 1..]))
+}
+
+fn class_contains(class: &[char], ch: char) -> bool {
+    let mut index = 0;
+    while index < class.len() {
+        if index + 2 < class.len() && class[index + 1] == '-' {
+            if class[index] <= ch && ch <= class[index + 2] {
+                return true;
+            }
+            index += 3;
+        } else {
+            if class[index] == ch {
+                return true;
+            }
+            index += 1;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{glob_match, path_matches, path_or_directory_matches};
+
+    #[test]
+    fn matches_wildcards_within_components() {
+        assert!(glob_match("*.pb.go", "api.pb.go"));
+        assert!(!glob_match("*.pb.go", "proto/api.pb.go"));
+        assert!(glob_match("src/?.rs", "src/a.rs"));
+        assert!(glob_match("src/[a-c]*.rs", "src/b_mod.rs"));
+        assert!(!glob_match("src/[!a-c]*.rs", "src/b_mod.rs"));
+    }
+
+    #[test]
+    fn matches_double_star_across_directories() {
+        assert!(glob_match("vendor/**", "vendor/github.com/x/y.go"));
+        assert!(glob_match("**/generated/*.ts", "web/generated/api.ts"));
+        assert!(glob_match("**/generated/*.ts", "generated/api.ts"));
+        assert!(glob_match("src/**/test_*.py", "src/a/b/test_x.py"));
+        assert!(!glob_match("src/**/test_*.py", "lib/test_x.py"));
+    }
+
+    #[test]
+    fn matches_bare_patterns_against_file_names() {
+        assert!(path_matches("*_test.go", "pkg/api/handler_test.go"));
+        assert!(path_matches(
+            "/db/migrations/*",
+            "db/migrations/0001_init.py"
+        ));
+        assert!(!path_matches("migrations/*", "app/migrations/0001_init.py"));
+    }
+
+    #[test]
+    fn matches_directories_like_gitignore() {
+        assert!(path_or_directory_matches(
+            "migrations",
+            "app/migrations/0001_init.py"
+        ));
+        assert!(path_or_directory_matches("vendor/", "vendor/x/y.go"));
+        assert!(!path_or_directory_matches("vendor/", "src/vendor"));
+        assert!(path_or_directory_matches("/src/gen", "src/gen/api.rs"));
+        assert!(!path_or_directory_matches("/src/gen", "lib/src/gen/api.rs"));
+        assert!(path_or_directory_matches("*.rs", "src/lib.rs"));
+        assert!(!path_or_directory_matches(
+            "migrations",
+            "app/migrations_old.py"
+        ));
+    }
+}
@@ -1,3 +1,96 @@
+use super::source::SourceTree;
+
 pub fn normalize_path(path: &str) -> String {
     path.replace('\\', "/")
 }
+
+/// Removes the first matching prefix, e.g. a Perforce depot root or repository checkout path.
+/// Prefixes match whole components, so `//depot/main` leaves `//depot/mainline/x.c` alone.
+pub fn strip_path_prefix<'a>(path: &'a str, prefixes: &[String]) -> &'a str {
+    prefixes
+        .iter()
+        .find_map(|prefix| {
+            let rest = path.strip_prefix(prefix.as_str())?;
+            (rest.is_empty() || rest.starts_with('/') || prefix.ends_with('/')).then_some(rest)
+        })
+        .map_or(path, |stripped| stripped.trim_start_matches('/'))
+}
+
+/// The longest trailing part of `path` that names a file in `source`, so that a coverage key
+/// recorded elsewhere, like `/ci/build/src/a.rs`, becomes the repository path `src/a.rs`.
+pub fn existing_suffix(path: &str, source: &SourceTree) -> Option<String> {
+    let components: Vec<&str> = path
+        .split('/')
+        .filter(|component| !component.is_empty() && *component != ".")
+        .collect();
+    (0..components.len()).find_map(|start| {
+        let candidate = components[start..].join("/");
+        source.is_file(&candidate).then_some(candidate)
+    })
+}
+
+/// A `--path-map FROM=TO` rule that rewrites the leading directories of coverage paths.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathMap {
+    pub from: String,
+    pub to: String,
+}
+
+impl PathMap {
+    /// Rewrites `path` when it equals `from` or lies below it; `from` matches whole components.
+    pub fn apply(&self, path: &str) -> Option<String> {
+        let rest = path.strip_prefix(self.from.as_str())?;
+        if !rest.is_empty() && !rest.starts_with('/') && !self.from.ends_with('/') {
+            return None;
+        }
+        let rest = rest.trim_start_matches('/');
+        let to = self.to.trim_end_matches('/');
+        Some(match (to.is_empty(), rest.is_empty()) {
+            (true, _) => rest.to_string(),
+            (false, true) => to.to_string(),
+            (false, false) => format!("{to}/{rest}"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{strip_path_prefix, PathMap};
+
+    #[test]
+    fn strips_first_matching_prefix() {
+        let prefixes = vec!["//depot/main".to_string(), "trunk/".to_string()];
+        assert_eq!(
+            strip_path_prefix("//depot/main/src/a.c", &prefixes),
+            "src/a.c"
+        );
+        assert_eq!(strip_path_prefix("trunk/src/b.c", &prefixes), "src/b.c");
+        assert_eq!(strip_path_prefix("src/c.c", &prefixes), "src/c.c");
+        assert_eq!(
+            strip_path_prefix("//depot/mainline/x.c", &prefixes),
+            "//depot/mainline/x.c"
+        );
+    }
+
+    #[test]
+    fn maps_leading_directories() {
+        let map = PathMap {
+            from: "/builds/group/project".to_string(),
+            to: String::new(),
+        };
+        assert_eq!(
+            map.apply("/builds/group/project/src/a.py").as_deref(),
+            Some("src/a.py")
+        );
+        assert_eq!(map.apply("/builds/group/project2/src/a.py"), None);
+
+        let map = PathMap {
+            from: "/app/".to_string(),
+            to: "services/api".to_string(),
+        };
+        assert_eq!(
+            map.apply("/app/main.go").as_deref(),
+            Some("services/api/main.go")
+        );
+    }
+}
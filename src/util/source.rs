@@ -0,0 +1,63 @@
+use std::cell::OnceCell;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use crate::vcs::git;
+
+/// Where the new side of the change is read from: a directory, such as `--new-tree` or the working
+/// tree, or the `--head` commit when the diff comes from git. Paths are relative to its root.
+#[derive(Debug)]
+pub enum SourceTree {
+    Directory(PathBuf),
+    Commit {
+        sha: String,
+        files: OnceCell<HashSet<String>>,
+    },
+}
+
+impl Default for SourceTree {
+    fn default() -> Self {
+        Self::Directory(PathBuf::from("."))
+    }
+}
+
+impl SourceTree {
+    /// Reads files from `sha`, which must come from `git::resolve_commit`.
+    pub fn commit(sha: String) -> Self {
+        Self::Commit {
+            sha,
+            files: OnceCell::new(),
+        }
+    }
+
+    pub fn is_file(&self, path: &str) -> bool {
+        match self {
+            Self::Directory(root) => root.join(path).is_file(),
+            Self::Commit { sha, files } => files
+                .get_or_init(|| {
+                    git::list_files(sha)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .collect()
+                })
+                .contains(path),
+        }
+    }
+
+    /// The contents of `path`, or `None` when it is not a readable file.
+    pub fn read(&self, path: &str) -> Option<Vec<u8>> {
+        match self {
+            Self::Directory(root) => std::fs::read(root.join(path)).ok(),
+            Self::Commit { sha, .. } => {
+                if !self.is_file(path) {
+                    return None;
+                }
+                git::show_file(sha, path).ok()
+            }
+        }
+    }
+
+    pub fn read_to_string(&self, path: &str) -> Option<String> {
+        String::from_utf8(self.read(path)?).ok()
+    }
+}
@@ -0,0 +1,102 @@
+use std::io::Cursor;
+use std::process::Command;
+
+use crate::diff::git::parse_unified_diff;
+use crate::diff::types::ChangedFile;
+
+/// Runs `git diff` in the current directory and parses its output, including the contents of
+/// changed submodules. `revisions` must come from `resolve_commit`, optionally joined with `...`.
+pub fn diff(revisions: &[&str]) -> Result<Vec<ChangedFile>, String> {
+    let stdout = run_git(
+        &[
+            &[
+                "diff",
+                "--no-color",
+                "--no-ext-diff",
+                "--find-renames",
+                "--submodule=diff",
+                // The parser expects the default prefixes, whatever `diff.noprefix` or
+                // `diff.mnemonicPrefix` say.
+                "--src-prefix=a/",
+                "--dst-prefix=b/",
+                "--end-of-options",
+            ],
+            revisions,
+        ]
+        .concat(),
+    )?;
+    parse_unified_diff(Cursor::new(stdout)).map_err(|err| err.to_string())
+}
+
+/// Resolves a user-supplied revision to a commit SHA, so that it can never be read as an option.
+pub fn resolve_commit(revision: &str) -> Result<String, String> {
+    if revision.starts_with('-') {
+        return Err(format!(
+            "Invalid revision {revision}: must not start with '-'"
+        ));
+    }
+    let stdout = run_git(&[
+        "rev-parse",
+        "--verify",
+        "--quiet",
+        "--end-of-options",
+        &format!("{revision}^{{commit}}"),
+    ])
+    .map_err(|_| format!("Unknown revision {revision}"))?;
+    Ok(String::from_utf8_lossy(&stdout).trim().to_string())
+}
+
+/// The committer time of `revision`, in seconds since the Unix epoch.
+pub fn commit_time(revision: &str) -> Result<u64, String> {
+    let commit = resolve_commit(revision)?;
+    let stdout = run_git(&[
+        "log",
+        "-1",
+        "--format=%ct",
+        "--end-of-options",
+        &commit,
+        "--",
+    ])?;
+    String::from_utf8_lossy(&stdout)
+        .trim()
+        .parse()
+        .map_err(|err| format!("Unexpected commit time for {revision}: {err}"))
+}
+
+/// The contents of `path` at `commit`, which must come from `resolve_commit`.
+pub fn show_file(commit: &str, path: &str) -> Result<Vec<u8>, String> {
+    run_git(&["show", "--no-textconv", &format!("{commit}:{path}")])
+}
+
+/// Every file tracked at `commit`, relative to the repository root.
+pub fn list_files(commit: &str) -> Result<Vec<String>, String> {
+    let stdout = run_git(&[
+        "ls-tree",
+        "-r",
+        "-z",
+        "--name-only",
+        "--full-tree",
+        "--end-of-options",
+        commit,
+    ])?;
+    Ok(stdout
+        .split(|&byte| byte == 0)
+        .filter(|path| !path.is_empty())
+        .map(|path| String::from_utf8_lossy(path).into_owned())
+        .collect())
+}
+
+fn run_git(args: &[&str]) -> Result<Vec<u8>, String> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .map_err(|err| format!("Failed to run git: {err}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
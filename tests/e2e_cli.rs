@@ -88,3 +88,439 @@ fn e2e_cli_reports_changed_line_totals_for_same_name() {
 
     let _ = std::fs::remove_file(&report_path);
 }
+
+#[test]
+fn e2e_cli_reports_changed_line_totals_for_context_diff() {
+    let diff_path = fixture_path("tests/fixtures/context_diff.diff");
+    let coverage_path = fixture_path("tests/fixtures/coverage_clover.xml");
+    let report_path = unique_report_path();
+
+    let output_target = format!("json={}", report_path.display());
+    let output = Command::new(env!("CARGO_BIN_EXE_diff-coverage"))
+        .arg("--diff-file")
+        .arg(&diff_path)
+        .arg(&coverage_path)
+        .arg("--output")
+        .arg(output_target)
+        .output()
+        .expect("run diff-coverage");
+
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let report_text = std::fs::read_to_string(&report_path).expect("read report");
+    let payload: serde_json::Value = serde_json::from_str(&report_text).expect("parse json");
+
+    assert_eq!(payload["total_changed"], 2);
+    assert_eq!(payload["total_covered"], 1);
+
+    let _ = std::fs::remove_file(&report_path);
+}
+
+#[test]
+fn e2e_cli_rebases_patch_series_from_mbox() {
+    let diff_path = fixture_path("tests/fixtures/series.mbox");
+    let coverage_path = fixture_path("tests/fixtures/coverage_clover.xml");
+    let report_path = unique_report_path();
+
+    let output_target = format!("json={}", report_path.display());
+    let output = Command::new(env!("CARGO_BIN_EXE_diff-coverage"))
+        .arg("--diff-file")
+        .arg(&diff_path)
+        .arg(&coverage_path)
+        .arg("--output")
+        .arg(output_target)
+        .output()
+        .expect("run diff-coverage");
+
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let report_text = std::fs::read_to_string(&report_path).expect("read report");
+    let payload: serde_json::Value = serde_json::from_str(&report_text).expect("parse json");
+
+    // Line 11 from the first patch moves to 16 once the second patch adds five lines above it.
+    assert_eq!(payload["total_changed"], 1);
+    assert_eq!(payload["total_covered"], 0);
+    assert_eq!(payload["uncovered_files"][0]["uncovered_lines"][0], 16);
+
+    let _ = std::fs::remove_file(&report_path);
+}
+
+#[test]
+fn e2e_cli_checks_selected_lines_without_diff() {
+    let coverage_path = fixture_path("tests/fixtures/coverage_clover.xml");
+    let report_path = unique_report_path();
+
+    let output_target = format!("json={}", report_path.display());
+    let output = Command::new(env!("CARGO_BIN_EXE_diff-coverage"))
+        .arg("--lines")
+        .arg("src/Calculator.php:10-11,16")
+        .arg(&coverage_path)
+        .arg("--output")
+        .arg(output_target)
+        .output()
+        .expect("run diff-coverage");
+
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let report_text = std::fs::read_to_string(&report_path).expect("read report");
+    let payload: serde_json::Value = serde_json::from_str(&report_text).expect("parse json");
+
+    assert_eq!(payload["total_changed"], 2);
+    assert_eq!(payload["total_covered"], 1);
+    assert_eq!(payload["uncovered_files"][0]["uncovered_lines"][0], 16);
+
+    let _ = std::fs::remove_file(&report_path);
+}
+
+#[test]
+fn e2e_cli_fails_on_changed_functions_never_called() {
+    let coverage_path = fixture_path("tests/fixtures/coverage_clover.xml");
+    let report_path = unique_report_path();
+
+    let output_target = format!("json={}", report_path.display());
+    let output = Command::new(env!("CARGO_BIN_EXE_diff-coverage"))
+        .arg("--lines")
+        .arg("src/Calculator.php:11,14,16")
+        .arg(&coverage_path)
+        .arg("--fail-on-uncalled-functions")
+        .arg("--output")
+        .arg(output_target)
+        .output()
+        .expect("run diff-coverage");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("1 changed function(s)"));
+
+    let report_text = std::fs::read_to_string(&report_path).expect("read report");
+    let payload: serde_json::Value = serde_json::from_str(&report_text).expect("parse json");
+
+    assert_eq!(payload["uncalled_functions"][0]["name"], "add");
+    assert_eq!(payload["uncalled_functions"][0]["start_line"], 14);
+    assert!(payload["uncalled_functions"][1].is_null());
+
+    let _ = std::fs::remove_file(&report_path);
+}
+
+#[test]
+fn e2e_cli_reads_github_pull_request_files_json() {
+    let diff_path = fixture_path("tests/fixtures/github_pr_files.json");
+    let coverage_path = fixture_path("tests/fixtures/coverage_clover.xml");
+    let report_path = unique_report_path();
+
+    let output_target = format!("json={}", report_path.display());
+    let output = Command::new(env!("CARGO_BIN_EXE_diff-coverage"))
+        .arg("--diff-file")
+        .arg(&diff_path)
+        .arg(&coverage_path)
+        .arg("--output")
+        .arg(output_target)
+        .output()
+        .expect("run diff-coverage");
+
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let report_text = std::fs::read_to_string(&report_path).expect("read report");
+    let payload: serde_json::Value = serde_json::from_str(&report_text).expect("parse json");
+
+    assert_eq!(payload["total_changed"], 2);
+    assert_eq!(payload["total_covered"], 1);
+
+    let _ = std::fs::remove_file(&report_path);
+}
+
+fn git(repo: &std::path::Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(repo)
+        .status()
+        .expect("run git");
+    assert!(status.success(), "git {args:?} failed");
+}
+
+#[test]
+fn e2e_cli_reports_lines_changed_since_reviewed_head() {
+    let repo = unique_report_path().with_extension("repo");
+    std::fs::create_dir_all(repo.join("src")).expect("create repo");
+    let lib = repo.join("src/lib.rs");
+    let write_lines = |count: u32| {
+        let text: String = (1..=count).map(|line| format!("line {line}\n")).collect();
+        std::fs::write(&lib, text).expect("write source");
+    };
+
+    git(&repo, &["init", "-q"]);
+    write_lines(2);
+    git(&repo, &["add", "."]);
+    git(&repo, &["commit", "-q", "-m", "base"]);
+    git(&repo, &["tag", "base"]);
+    write_lines(4);
+    git(&repo, &["commit", "-q", "-am", "reviewed"]);
+    git(&repo, &["tag", "reviewed"]);
+    write_lines(6);
+    git(&repo, &["commit", "-q", "-am", "head"]);
+
+    std::fs::write(
+        repo.join("coverage.xml"),
+        r#"<coverage><packages><package><classes>
+<class filename="src/lib.rs"><lines>
+<line number="3" hits="1"/><line number="4" hits="1"/>
+<line number="5" hits="1"/><line number="6" hits="0"/>
+</lines></class>
+</classes></package></packages></coverage>"#,
+    )
+    .expect("write coverage");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_diff-coverage"))
+        .args(["--base", "base", "--reviewed-head", "reviewed"])
+        .args(["coverage.xml", "--output", "json=report.json"])
+        .current_dir(&repo)
+        .output()
+        .expect("run diff-coverage");
+
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let report_text = std::fs::read_to_string(repo.join("report.json")).expect("read report");
+    let payload: serde_json::Value = serde_json::from_str(&report_text).expect("parse json");
+
+    assert_eq!(payload["total_changed"], 4);
+    assert_eq!(payload["total_covered"], 3);
+    assert_eq!(payload["incremental"]["total_changed"], 2);
+    assert_eq!(payload["incremental"]["total_covered"], 1);
+
+    let _ = std::fs::remove_dir_all(&repo);
+}
+
+#[test]
+fn e2e_cli_reads_exclusion_markers_from_the_head_revision() {
+    let repo = unique_report_path().with_extension("repo");
+    std::fs::create_dir_all(repo.join("src")).expect("create repo");
+    let lib = repo.join("src/lib.rs");
+    std::fs::write(&lib, "fn a() {}\n").expect("write source");
+    git(&repo, &["init", "-q"]);
+    git(&repo, &["add", "."]);
+    git(&repo, &["commit", "-q", "-m", "base"]);
+    git(&repo, &["tag", "base"]);
+    std::fs::write(
+        &lib,
+        "fn a() {}\nfn debug() {} // LCOV_EXCL_LINE\nfn b() {}\n",
+    )
+    .expect("write source");
+    git(&repo, &["commit", "-q", "-am", "feature"]);
+    git(&repo, &["tag", "feature"]);
+    // The working tree no longer has the marker; only the --head revision does.
+    git(&repo, &["checkout", "-q", "base"]);
+
+    std::fs::write(
+        repo.join("coverage.xml"),
+        r#"<coverage><packages><package><classes>
+<class filename="src/lib.rs"><lines>
+<line number="2" hits="0"/><line number="3" hits="1"/>
+</lines></class>
+</classes></package></packages></coverage>"#,
+    )
+    .expect("write coverage");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_diff-coverage"))
+        .args(["--base", "base", "--head", "feature"])
+        .args(["coverage.xml", "--output", "json=report.json"])
+        .current_dir(&repo)
+        .output()
+        .expect("run diff-coverage");
+
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let report_text = std::fs::read_to_string(repo.join("report.json")).expect("read report");
+    let payload: serde_json::Value = serde_json::from_str(&report_text).expect("parse json");
+
+    assert_eq!(payload["excluded_marked_lines"], 1);
+    assert_eq!(payload["total_changed"], 1);
+    assert_eq!(payload["total_covered"], 1);
+
+    let _ = std::fs::remove_dir_all(&repo);
+}
+
+#[test]
+fn e2e_cli_fails_on_stale_coverage() {
+    let repo = unique_report_path().with_extension("repo");
+    std::fs::create_dir_all(repo.join("src")).expect("create repo");
+    std::fs::write(repo.join("src/lib.rs"), "fn a() {\n    b();\n}\n").expect("write source");
+    git(&repo, &["init", "-q"]);
+    git(&repo, &["add", "."]);
+    git(&repo, &["commit", "-q", "-m", "head"]);
+
+    std::fs::write(
+        repo.join("coverage.xml"),
+        r#"<coverage timestamp="1000"><packages><package><classes>
+<class filename="src/lib.rs"><lines>
+<line number="2" hits="1"/><line number="7" hits="0"/>
+</lines></class>
+</classes></package></packages></coverage>"#,
+    )
+    .expect("write coverage");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_diff-coverage"))
+        .args(["--lines", "src/lib.rs:2", "coverage.xml"])
+        .args(["--stale-coverage", "fail", "--output", "json=report.json"])
+        .current_dir(&repo)
+        .output()
+        .expect("run diff-coverage");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("coverage looks stale: 2 sign(s)"));
+
+    let report_text = std::fs::read_to_string(repo.join("report.json")).expect("read report");
+    let payload: serde_json::Value = serde_json::from_str(&report_text).expect("parse json");
+
+    assert!(payload["stale_signals"][0]["path"].is_null());
+    assert_eq!(payload["stale_signals"][1]["path"], "src/lib.rs");
+    assert_eq!(
+        payload["stale_signals"][1]["reason"],
+        "line 7 is measured but the file has 3 lines"
+    );
+
+    let _ = std::fs::remove_dir_all(&repo);
+}
+
+#[test]
+fn e2e_cli_rejects_revisions_that_look_like_options() {
+    let repo = unique_report_path().with_extension("repo");
+    std::fs::create_dir_all(repo.join("src")).expect("create repo");
+    git(&repo, &["init", "-q"]);
+    git(&repo, &["config", "diff.noprefix", "true"]);
+    std::fs::write(repo.join("src/lib.rs"), "line 1\n").expect("write source");
+    git(&repo, &["add", "."]);
+    git(&repo, &["commit", "-q", "-m", "base"]);
+    git(&repo, &["tag", "base"]);
+    std::fs::write(repo.join("src/lib.rs"), "line 1\nline 2\n").expect("write source");
+    git(&repo, &["commit", "-q", "-am", "head"]);
+    std::fs::write(
+        repo.join("coverage.xml"),
+        r#"<coverage><packages><package><classes>
+<class filename="src/lib.rs"><lines><line number="2" hits="1"/></lines></class>
+</classes></package></packages></coverage>"#,
+    )
+    .expect("write coverage");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_diff-coverage"))
+        .args(["--base", "base", "--reviewed-head=--output=injected.txt"])
+        .arg("coverage.xml")
+        .current_dir(&repo)
+        .output()
+        .expect("run diff-coverage");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(!repo.join("injected.txt").exists());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_diff-coverage"))
+        .args(["--lines", "src/lib.rs:2", "--head", "base", "coverage.xml"])
+        .current_dir(&repo)
+        .output()
+        .expect("run diff-coverage");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--head requires --base"));
+
+    // `diff.noprefix` must not change the paths read from git.
+    let output = Command::new(env!("CARGO_BIN_EXE_diff-coverage"))
+        .args([
+            "--base",
+            "base",
+            "coverage.xml",
+            "--output",
+            "json=report.json",
+        ])
+        .current_dir(&repo)
+        .output()
+        .expect("run diff-coverage");
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let report_text = std::fs::read_to_string(repo.join("report.json")).expect("read report");
+    let payload: serde_json::Value = serde_json::from_str(&report_text).expect("parse json");
+    assert_eq!(payload["total_changed"], 1);
+    assert_eq!(payload["total_covered"], 1);
+
+    let _ = std::fs::remove_dir_all(&repo);
+}
+
+#[test]
+fn e2e_cli_rejects_branch_gate_without_branch_data() {
+    let diff_path = fixture_path("tests/fixtures/git_diff.diff");
+    let coverage_path = fixture_path("tests/fixtures/coverage_cobertura.xml");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_diff-coverage"))
+        .arg("--diff-file")
+        .arg(&diff_path)
+        .arg(&coverage_path)
+        .args(["--fail-under-branches", "80"])
+        .output()
+        .expect("run diff-coverage");
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("branch data"));
+}
+
+#[test]
+fn e2e_cli_scopes_project_totals_by_repository_paths() {
+    let repo = unique_report_path().with_extension("repo");
+    std::fs::create_dir_all(repo.join("src")).expect("create repo");
+    std::fs::write(repo.join("src/a.rs"), "fn a() {}\nfn b() {}\n").expect("write source");
+    std::fs::write(
+        repo.join("coverage.xml"),
+        r#"<coverage><packages><package><classes>
+<class filename="/ci/build/src/a.rs"><lines>
+<line number="1" hits="0"/><line number="2" hits="1"/>
+</lines></class>
+</classes></package></packages></coverage>"#,
+    )
+    .expect("write coverage");
+
+    let run = |include: &str| {
+        Command::new(env!("CARGO_BIN_EXE_diff-coverage"))
+            .args([
+                "--lines",
+                "src/a.rs:2",
+                "coverage.xml",
+                "--include",
+                include,
+            ])
+            .args(["--total-scope", "filtered", "--fail-under-total", "90"])
+            .current_dir(&repo)
+            .output()
+            .expect("run diff-coverage")
+    };
+
+    let output = run("src/**");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("50.00%"));
+
+    let output = run("lib/**");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no measured lines"));
+
+    let _ = std::fs::remove_dir_all(&repo);
+}